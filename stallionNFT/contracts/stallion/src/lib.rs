@@ -1,20 +1,88 @@
 #![no_std]
-use soroban_sdk::{contract, contractimpl, contracttype, Address, String, Env, Vec, symbol_short, Bytes};
+use soroban_sdk::{contract, contracterror, contractimpl, contracttype, panic_with_error, token, xdr::ToXdr, Address, IntoVal, String, Env, Map, Symbol, Vec, symbol_short, Bytes, BytesN};
 
 // Define the StallionNFT contract
 #[contract]
 pub struct StallionNFT;
 
+// Errors returned by the contract
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Error {
+    TokenNotFound = 1,   // The token has never been minted or has been burned
+    PriceOverflow = 2,   // The sale price is too large to compute the royalty on
+    OnchainMetadata = 3, // The token was minted with on-chain metadata and has no image or metadata URL
+}
+
 // Define the keys used for storing data in the contract's storage
 #[contracttype]
 #[derive(Clone, Debug)]
 pub enum DataKey {
     Owner(i128),          // Key for storing the owner of a token
-    TokenCount,           // Key for storing the total number of tokens minted
+    TokenCount,           // Key for storing the last token ID assigned by public minting
     Approvals(i128),      // Key for storing approved addresses for a token
     Whitelist,            // Key for storing the whitelist of addresses allowed to mint
-    Admin(Address),       // Key for storing the admin address
+    Admin,                // Key for storing the admin address
     HasMinted(Address),   // Key for storing whether an address has minted a token
+    OwnedTokens(Address), // Key for storing the token IDs held by an address
+    Royalty,              // Key for storing the collection-default royalty
+    TokenRoyalty(i128),   // Key for storing a per-token royalty override
+    TokenMeta(i128),      // Key for storing the minting record of a token
+    PermitNonce(Address), // Key for storing the next permit nonce of an owner
+    ReservedRange,        // Key for storing the inclusive range of token IDs reserved from public minting
+    OnchainMeta(i128),    // Key for storing the on-chain metadata of a token
+    ApprovalExpiry(i128), // Key for storing the expiry time of time-limited approvals for a token
+    CurrentPhase,         // Key for storing the active sale phase
+    PhaseCap(u32),        // Key for storing the maximum number of tokens mintable in a phase
+    PhaseMinted(u32),     // Key for storing the number of tokens minted in a phase
+    HoldingCap,           // Key for storing the maximum number of tokens a single address may hold
+    Exempt(Address),      // Key for storing whether an address is exempt from holding caps
+    SaleConfig,           // Key for storing the sale configuration
+    BurnedBy(Address),    // Key for storing the number of tokens an address has burned
+    RoyaltyEnforced,      // Key for storing whether royalties are enforced on-chain
+    LastMintTime,         // Key for storing the timestamp of the most recent mint
+    MintedAt(i128),       // Key for storing the original minter and mint timestamp of a token
+    ImageFrozen(i128),    // Key for storing whether a token's image URL is frozen
+    MetadataFrozen(i128), // Key for storing whether a token's metadata URL is frozen
+    FreeMintWindow,       // Key for storing the window in which whitelisted addresses mint for free
+    OperatorApproval(Address, Address), // Key for storing whether an operator may manage all of an owner's tokens
+    Blacklisted(Address), // Key for storing whether an operator is barred from moving tokens
+    PhaseConfig(u32),     // Key for storing the window and gating of a phase
+    PhaseCount,           // Key for storing the number of distinct phases configured
+    Paused,               // Key for storing whether public minting is paused
+    MintRateLimit,        // Key for storing the (max mints, window seconds) threshold of the circuit breaker
+    RecentMints,          // Key for storing the timestamps of mints inside the circuit breaker window
+    RefundPolicy,         // Key for storing the (window seconds, amount) of post-mint refunds
+    RoyaltyMarketplace(Address), // Key for storing whether a marketplace may use the royalty-enforcing sell path
+    GlobalTransferCount,  // Key for storing the number of transfers across the whole collection
+    MerkleRoot,           // Key for storing the merkle root of the allowlist
+    TokenSoulbound(i128), // Key for storing whether a token is bound to its owner
+    History(i128),        // Key for storing the most recent (owner, timestamp) entries of a token, starting at its mint
+    ContractUri,          // Key for storing the URI of the collection-level metadata
+    WhitelistMode,        // Key for storing how public mints are gated (open, vector, merkle or signature)
+    MintSigner,           // Key for storing the ed25519 public key that signs mint authorizations
+    TransferCount(i128),  // Key for storing the number of times a token has been transferred
+    MintCap,              // Key for storing the number of public mints allowed per address
+    MintCapOverride(Address), // Key for storing a per-address override of the mint cap
+    MintCount(Address),   // Key for storing the number of public mints made by an address
+    Tier(Address),        // Key for storing the whitelist tier assigned to an address
+    LastMintReceipt(Address), // Key for storing the receipt of an address's most recent public mint
+}
+
+// Define further storage keys, kept apart because a contracttype enum holds at most 50 variants
+#[contracttype]
+#[derive(Clone, Debug)]
+pub enum ExtDataKey {
+    ReserveLocked,        // Key for storing whether reserved minting has been permanently locked
+    PendingAdmin,         // Key for storing the proposed admin during a two-step handover
+    Revealed(i128),       // Key for storing whether a token has been revealed
+    MintPrice(i128),      // Key for storing the payment token and price paid for a token at public mint
+    Burned(i128),         // Key for storing a tombstone for a burned token so its ID is never reissued
+    MintNonce(Address),   // Key for storing the next mint-signature nonce of an address
+    HolderCount,          // Key for storing the number of addresses currently holding at least one token
+    HolderAt(u32),        // Key for storing the holder at a position in the holder set
+    HolderIndex(Address), // Key for storing the position of a holder in the holder set
 }
 
 // Structure to store minting information
@@ -27,6 +95,57 @@ pub struct MintTo {
     pub image: String,    // Image URL associated with the token
 }
 
+// Structure to store metadata held entirely on-chain
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct OnchainMeta {
+    pub name: String,                      // Name of the token
+    pub attributes: Vec<(String, String)>, // Trait type and value pairs
+}
+
+// Structure to store royalty information
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Royalty {
+    pub receiver: Address, // The address receiving royalty payments
+    pub bps: u32,          // Royalty rate in basis points (1/100th of a percent)
+}
+
+// Structure to store the sale configuration applied by `configure`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SaleConfig {
+    pub payment_token: Address,   // Token used to pay for mints
+    pub treasury: Address,        // Address receiving mint payments
+    pub price: i128,              // Price of a single mint in the payment token
+    pub sale_start: u64,          // Timestamp at which minting opens
+    pub sale_end: u64,            // Timestamp after which minting closes
+    pub sale_active: bool,        // Whether the sale is switched on
+    pub holding_cap: Option<u32>, // Maximum number of tokens a single address may hold, or None for no cap
+    pub royalty: Royalty,         // Collection-default royalty
+}
+
+// Structure describing a sale phase
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PhaseInfo {
+    pub cap: Option<u32>,      // Maximum number of tokens mintable in the phase, if capped
+    pub start: u64,            // Timestamp at which the phase opens
+    pub end: u64,              // Timestamp after which the phase closes
+    pub whitelist_gated: bool, // Whether only whitelisted addresses may mint in the phase
+}
+
+// Structure recording the outcome of a public mint
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MintReceipt {
+    pub token_id: i128,                // The ID of the minted token
+    pub timestamp: u64,                // Timestamp at which the token was minted
+    pub phase: u32,                    // Sale phase active at mint time
+    pub payment_token: Option<Address>, // Token the mint was paid in, if a sale is configured
+    pub price: i128,                   // Amount paid for the mint
+}
+
 // Implementation of the StallionNFT contract
 #[contractimpl]
 impl StallionNFT {
@@ -36,10 +155,45 @@ impl StallionNFT {
     const METADATA: &'static str = "https://ipfs.io/ipfs/bafkreibzw25uz3cxnpd4ditc2s7ngyea2hpq45s7psbs27dm3z6r57rzbe";
     const IMAGE: &'static str = "https://ipfs.io/ipfs/bafybeichocyvocmrrixgunzlrcnj4u7sbg3cst54mp3e3begu4qiphe3jq";
     const SUPPLY: i128 = 2000; // Maximum supply of tokens
+    const MAX_BPS: u32 = 10_000; // Basis points representing 100%
+    const MAX_ATTRIBUTES: u32 = 16; // Maximum number of on-chain attributes per token
+    const MAX_ATTRIBUTE_LEN: usize = 64; // Maximum length of an on-chain name, trait type or value
+    const MAX_JSON_LEN: usize = 3072; // Buffer size fitting the largest on-chain metadata document
+    const MAX_APPROVALS: u32 = 32; // Maximum number of operators approved on a single token
+    const MAX_HISTORY: u32 = 20; // Maximum number of ownership history entries kept per token
+    const MAX_BATCH: u32 = 50; // Maximum number of entries in a single batch admin call
+    const MAX_RATE_LIMIT_MINTS: u32 = 100; // Largest circuit breaker threshold, bounding the recent mint list
+    const WHITELIST_OPEN: u32 = 0; // Anyone may mint
+    const WHITELIST_VECTOR: u32 = 1; // Minters must be in the stored whitelist
+    const WHITELIST_MERKLE: u32 = 2; // Minters must prove membership of the merkle allowlist
+    const WHITELIST_SIGNATURE: u32 = 3; // Minters must present a signature from the mint signer
+
+    // Function to initialize the contract with an admin address, callable only once. The admin must
+    // authorize it, and deployment and initialization should be submitted in the same transaction so
+    // nobody else can claim the contract in between.
+    pub fn initialize(env: Env, admin: Address) {
+        admin.require_auth();
+        assert!(!env.storage().instance().has(&DataKey::Admin), "Contract already initialized");
+        env.storage().instance().set(&DataKey::Admin, &admin);
+    }
+
+    // Function to propose a new admin, who must accept before the handover takes effect
+    pub fn propose_admin(env: Env, admin: Address, new_admin: Address) {
+        Self::require_admin(&env, &admin);
+        env.storage().instance().set(&ExtDataKey::PendingAdmin, &new_admin);
+    }
+
+    // Function for the proposed admin to accept the handover
+    pub fn accept_admin(env: Env, new_admin: Address) {
+        new_admin.require_auth();
+        assert_eq!(Self::pending_admin(env.clone()), Some(new_admin.clone()), "Not the pending admin");
+        env.storage().instance().set(&DataKey::Admin, &new_admin);
+        env.storage().instance().remove(&ExtDataKey::PendingAdmin);
+    }
 
-    // Constructor to initialize the contract with an admin address
-    pub fn __constructor(env: Env, admin: Address) {
-        env.storage().instance().set(&DataKey::Admin(admin.clone()), &admin);
+    // Function to get the proposed admin, if a handover is in progress
+    pub fn pending_admin(env: Env) -> Option<Address> {
+        env.storage().instance().get(&ExtDataKey::PendingAdmin)
     }
 
     // Function to get the name of the NFT
@@ -62,6 +216,40 @@ impl StallionNFT {
         String::from_str(&env, Self::IMAGE)
     }
 
+    // Function to set the URI of the collection-level metadata (name, description, image, fee recipient)
+    pub fn set_contract_uri(env: Env, admin: Address, uri: String) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().set(&DataKey::ContractUri, &uri);
+    }
+
+    // Function to get the URI of the collection-level metadata, empty if not set
+    pub fn contract_uri(env: Env) -> String {
+        env.storage().persistent().get(&DataKey::ContractUri).unwrap_or_else(|| String::from_str(&env, ""))
+    }
+
+    // Function to check whether a named extension is available: always-on ones (enumerable, soulbound, permit)
+    // or ones the admin has configured (royalty, merkle, phased, sale, reserve, signature)
+    pub fn has_extension(env: Env, name: Symbol) -> bool {
+        let storage = env.storage().persistent();
+        if name == Symbol::new(&env, "enumerable") || name == symbol_short!("soulbound") || name == symbol_short!("permit") {
+            true
+        } else if name == symbol_short!("royalty") {
+            storage.has(&DataKey::Royalty)
+        } else if name == symbol_short!("merkle") {
+            storage.has(&DataKey::MerkleRoot)
+        } else if name == symbol_short!("phased") {
+            Self::phase_count(env.clone()) > 0
+        } else if name == symbol_short!("sale") {
+            storage.has(&DataKey::SaleConfig)
+        } else if name == symbol_short!("reserve") {
+            storage.has(&DataKey::ReservedRange)
+        } else if name == symbol_short!("signature") {
+            storage.has(&DataKey::MintSigner)
+        } else {
+            false
+        }
+    }
+
     // Function to get the owner of a specific token
     pub fn owner_of(env: Env, token_id: i128) -> Address {
         env.storage().persistent().get(&DataKey::Owner(token_id)).unwrap_or_else(|| {
@@ -69,14 +257,17 @@ impl StallionNFT {
         })
     }
 
-    // Function to add an address to the whitelist
-    pub fn add_to_whitelist(env: Env, address: Address) {
+    // Function to add an address to the whitelist, returning false if it was already present
+    pub fn add_to_whitelist(env: Env, admin: Address, address: Address) -> bool {
+        Self::require_admin(&env, &admin);
+
         let mut whitelist = env.storage().persistent().get::<DataKey, Vec<Address>>(&DataKey::Whitelist).unwrap_or_else(|| Vec::new(&env));
         if whitelist.contains(&address) {
-            panic!("Address is already whitelisted");
+            return false;
         }
         whitelist.push_back(address.clone());
         env.storage().persistent().set(&DataKey::Whitelist, &whitelist);
+        true
     }
 
     // Function to get the list of whitelisted addresses
@@ -84,18 +275,48 @@ impl StallionNFT {
         env.storage().persistent().get::<DataKey, Vec<Address>>(&DataKey::Whitelist).unwrap_or_else(|| Vec::new(&env))
     }
 
+    // Function to count the whitelisted addresses that have minted at least once.
+    // Scans the whole whitelist, so the cost grows with its length.
+    pub fn whitelist_claimed_count(env: Env) -> u32 {
+        let mut claimed = 0;
+        for address in Self::get_whitelist(env.clone()).iter() {
+            if env.storage().persistent().get::<DataKey, bool>(&DataKey::HasMinted(address)).unwrap_or(false) {
+                claimed += 1;
+            }
+        }
+        claimed
+    }
+
+    // Function to get whether an address is whitelisted and whether it has minted, in one call
+    pub fn whitelist_status(env: Env, address: Address) -> (bool, bool) {
+        let is_whitelisted = Self::get_whitelist(env.clone()).contains(&address);
+        let has_minted = env.storage().persistent().get::<DataKey, bool>(&DataKey::HasMinted(address))
+            .unwrap_or(false);
+        (is_whitelisted, has_minted)
+    }
+
+    // Function to assign whitelist tiers to several addresses in one call
+    pub fn assign_tiers(env: Env, admin: Address, entries: Vec<(Address, u32)>) {
+        Self::require_admin(&env, &admin);
+        assert!(entries.len() <= Self::MAX_BATCH, "Batch too large");
+        for (address, tier) in entries.iter() {
+            env.storage().persistent().set(&DataKey::Tier(address), &tier);
+        }
+    }
+
+    // Function to get the whitelist tier of an address, if one was assigned
+    pub fn tier_of(env: Env, address: Address) -> Option<u32> {
+        env.storage().persistent().get(&DataKey::Tier(address))
+    }
+
     // Function to remove an address from the whitelist
     pub fn remove_from_whitelist(env: Env, admin: Address, address: Address) {
-        admin.require_auth();
-
-        let stored_admin: Address = env.storage().instance().get(&DataKey::Admin(admin.clone()))
-            .expect("Admin address not set");
-        assert_eq!(admin, stored_admin, "Caller is not the admin");
+        Self::require_admin(&env, &admin);
 
         let mut whitelist = env.storage().persistent().get::<DataKey, Vec<Address>>(&DataKey::Whitelist)
             .expect("Whitelist does not exist");
-        if let Some(pos) = whitelist.iter().position(|x| x == address) {
-            whitelist.remove(pos.try_into().unwrap());
+        if let Some(pos) = whitelist.first_index_of(&address) {
+            whitelist.remove(pos);
             env.storage().persistent().set(&DataKey::Whitelist, &whitelist);
         } else {
             panic!("Address not whitelisted");
@@ -106,17 +327,21 @@ impl StallionNFT {
     pub fn is_approved(env: Env, operator: Address, token_id: i128) -> bool {
         let key = DataKey::Approvals(token_id);
         let approvals = env.storage().persistent().get::<DataKey, Vec<Address>>(&key).unwrap_or_else(|| Vec::new(&env));
-        approvals.contains(&operator)
+        if !approvals.contains(&operator) {
+            return false;
+        }
+        match Self::approval_expiries(&env, token_id).get(operator) {
+            Some(expires_at) => env.ledger().timestamp() <= expires_at,
+            None => true,
+        }
     }
 
     // Function to transfer a token from one address to another
     pub fn transfer(env: Env, owner: Address, to: Address, token_id: i128) {
         owner.require_auth();
-        let actual_owner = Self::owner_of(env.clone(), token_id);
+        let actual_owner = Self::existing_owner(&env, token_id);
         if owner == actual_owner {
-            env.storage().persistent().set(&DataKey::Owner(token_id), &to);
-            env.storage().persistent().remove(&DataKey::Approvals(token_id));
-            env.events().publish((symbol_short!("Transfer"),), (owner, to, token_id));
+            Self::move_token(&env, owner, to, token_id);
         } else {
             panic!("Not the token owner");
         }
@@ -124,93 +349,430 @@ impl StallionNFT {
 
     // Function to mint a new token to a whitelisted address
     pub fn mint(env: Env, to: Address) {
-        let whitelist = env.storage().persistent().get::<DataKey, Vec<Address>>(&DataKey::Whitelist)
-            .expect("Whitelist not found");
-        assert!(whitelist.contains(&to), "Address not whitelisted");
+        let token_id = Self::mint_public(&env, &to, false);
 
-        // Check if the address has already minted a token
-        let has_minted = env.storage().persistent().get::<DataKey, bool>(&DataKey::HasMinted(to.clone()))
-            .unwrap_or(false);
-        assert!(!has_minted, "Address has already minted a token");
+        Self::store_default_meta(&env, to, token_id);
+    }
 
-        let mut token_count: i128 = env.storage().persistent().get(&DataKey::TokenCount).unwrap_or(0);
-        assert!(token_count < Self::SUPPLY, "Maximum token supply reached");
-        token_count += 1;
+    // Function to mint a new soulbound token to a whitelisted address, which can never be transferred
+    pub fn mint_soulbound(env: Env, to: Address) {
+        let token_id = Self::mint_public(&env, &to, false);
 
-        let mint_to = MintTo {
-            address: to.clone(),
-            token_id: token_count,
-            metadata: Self::token_uri(env.clone()),
-            image: Self::token_image(env.clone()),
-        };
+        Self::store_default_meta(&env, to, token_id);
+        env.storage().persistent().set(&DataKey::TokenSoulbound(token_id), &true);
+    }
 
-        env.storage().persistent().set(&DataKey::Approvals(token_count), &mint_to);
+    // Function to check whether a token is soulbound
+    pub fn is_soulbound(env: Env, token_id: i128) -> bool {
+        env.storage().persistent().get(&DataKey::TokenSoulbound(token_id)).unwrap_or(false)
+    }
 
-        env.storage().persistent().set(&DataKey::TokenCount, &token_count);
-        env.storage().persistent().set(&DataKey::Owner(token_count), &to);
+    // Function to mint a new token to a whitelisted address with its metadata stored fully on-chain.
+    // Such tokens have no image or metadata URL; read them through token_json instead.
+    pub fn mint_onchain(env: Env, to: Address, name: String, attributes: Vec<(String, String)>) {
+        Self::check_onchain_text(&name);
+        assert!(attributes.len() <= Self::MAX_ATTRIBUTES, "Too many attributes");
+        for (trait_type, value) in attributes.iter() {
+            Self::check_onchain_text(&trait_type);
+            Self::check_onchain_text(&value);
+        }
 
-        // Mark the address as having minted a token
-        env.storage().persistent().set(&DataKey::HasMinted(to.clone()), &true);
+        let token_id = Self::mint_public(&env, &to, false);
+        env.storage().persistent().set(&DataKey::OnchainMeta(token_id), &OnchainMeta { name, attributes });
+    }
+
+    // Function to get the timestamp of the most recent mint, if anything has been minted
+    pub fn last_mint_at(env: Env) -> Option<u64> {
+        env.storage().persistent().get(&DataKey::LastMintTime)
+    }
+
+    // Function to set the merkle root of the allowlist used by mint_with_proof
+    pub fn set_merkle_root(env: Env, admin: Address, root: BytesN<32>) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().set(&DataKey::MerkleRoot, &root);
+    }
+
+    // Function to check a merkle proof of allowlist membership without minting
+    pub fn verify_proof(env: Env, address: Address, proof: Vec<BytesN<32>>) -> bool {
+        match env.storage().persistent().get::<DataKey, BytesN<32>>(&DataKey::MerkleRoot) {
+            Some(root) => Self::merkle_root_of(&env, &address, &proof) == root,
+            None => false,
+        }
+    }
+
+    // Function to mint a new token to an address proven to be in the merkle allowlist
+    pub fn mint_with_proof(env: Env, to: Address, proof: Vec<BytesN<32>>) {
+        assert_eq!(Self::whitelist_mode(env.clone()), Self::WHITELIST_MERKLE, "Merkle minting is not enabled");
+        assert!(Self::verify_proof(env.clone(), to.clone(), proof), "Invalid merkle proof");
+        let token_id = Self::mint_public(&env, &to, true);
+
+        Self::store_default_meta(&env, to, token_id);
+    }
+
+    // Function to set the ed25519 public key whose signatures authorize mints in signature mode
+    pub fn set_mint_signer(env: Env, admin: Address, public_key: BytesN<32>) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().set(&DataKey::MintSigner, &public_key);
+    }
+
+    // Function to get the nonce the next mint signature for an address must use
+    pub fn mint_nonce(env: Env, to: Address) -> u64 {
+        env.storage().persistent().get(&ExtDataKey::MintNonce(to)).unwrap_or(0)
+    }
+
+    // Function to mint a new token to an address authorized by the mint signer. The signer signs the XDR of
+    // (contract, to, deadline, nonce) with the key set through set_mint_signer, so each signature mints once.
+    pub fn mint_with_signature(env: Env, to: Address, deadline: u64, signature: BytesN<64>) {
+        assert_eq!(Self::whitelist_mode(env.clone()), Self::WHITELIST_SIGNATURE, "Signature minting is not enabled");
+        assert!(env.ledger().timestamp() <= deadline, "Signature expired");
+        let public_key: BytesN<32> = env.storage().persistent().get(&DataKey::MintSigner).expect("Mint signer not set");
+        let nonce = Self::mint_nonce(env.clone(), to.clone());
+        let payload = (env.current_contract_address(), to.clone(), deadline, nonce).to_xdr(&env);
+        env.crypto().ed25519_verify(&public_key, &payload, &signature);
+        env.storage().persistent().set(&ExtDataKey::MintNonce(to.clone()), &(nonce + 1));
+        let token_id = Self::mint_public(&env, &to, true);
+
+        Self::store_default_meta(&env, to, token_id);
+    }
 
-        env.events().publish((symbol_short!("Mint"),), (to, token_count));
+    // Function to choose how public mints are gated: 0 open, 1 stored whitelist, 2 merkle proof, 3 signature
+    pub fn set_whitelist_mode(env: Env, admin: Address, mode: u32) {
+        Self::require_admin(&env, &admin);
+        assert!(mode <= Self::WHITELIST_SIGNATURE, "Invalid whitelist mode");
+        env.storage().persistent().set(&DataKey::WhitelistMode, &mode);
+    }
+
+    // Function to get how public mints are gated, defaulting to the stored whitelist until set_whitelist_mode is called
+    pub fn whitelist_mode(env: Env) -> u32 {
+        env.storage().persistent().get(&DataKey::WhitelistMode).unwrap_or(Self::WHITELIST_VECTOR)
+    }
+
+    // Function to get the on-chain metadata of a token as a JSON document
+    pub fn token_json(env: Env, token_id: i128) -> String {
+        let meta: OnchainMeta = env.storage().persistent().get(&DataKey::OnchainMeta(token_id))
+            .expect("On-chain metadata not found for this token");
+
+        let mut json = [0u8; Self::MAX_JSON_LEN];
+        let mut len = 0;
+        Self::push_bytes(&mut json, &mut len, b"{\"name\":\"");
+        Self::push_string(&mut json, &mut len, &meta.name);
+        Self::push_bytes(&mut json, &mut len, b"\",\"attributes\":[");
+        for (i, (trait_type, value)) in meta.attributes.iter().enumerate() {
+            if i > 0 {
+                Self::push_bytes(&mut json, &mut len, b",");
+            }
+            Self::push_bytes(&mut json, &mut len, b"{\"trait_type\":\"");
+            Self::push_string(&mut json, &mut len, &trait_type);
+            Self::push_bytes(&mut json, &mut len, b"\",\"value\":\"");
+            Self::push_string(&mut json, &mut len, &value);
+            Self::push_bytes(&mut json, &mut len, b"\"}");
+        }
+        Self::push_bytes(&mut json, &mut len, b"]}");
+
+        String::from_str(&env, core::str::from_utf8(&json[..len]).expect("Metadata is not valid UTF-8"))
     }
 
     // Function to retrieve the image URL for a given token ID
     pub fn get_token_image(env: Env, token_id: i128) -> String {
         // Retrieve the MintTo struct from storage and return the image URL
-        let mint_to: MintTo = env.storage().persistent().get(&DataKey::Approvals(token_id))
-            .expect("MintTo struct not found for this token");
+        let mint_to: MintTo = Self::token_meta(&env, token_id);
         mint_to.image
     }
 
     // Function to retrieve the metadata URL for a given token ID
     pub fn get_token_metadata(env: Env, token_id: i128) -> String {
         // Retrieve the MintTo struct from storage and return the metadata URL
-        let mint_to: MintTo = env.storage().persistent().get(&DataKey::Approvals(token_id))
-            .expect("MintTo struct not found for this token");
+        let mint_to: MintTo = Self::token_meta(&env, token_id);
         mint_to.metadata
     }
 
+    // Function to retrieve the (image, metadata) URL pair for a given token ID, in the shape
+    // older integrations read from the hello-world contract's Approvals(token_id) entry
+    pub fn get_token_uris(env: Env, token_id: i128) -> (String, String) {
+        let mint_to: MintTo = Self::token_meta(&env, token_id);
+        (mint_to.image, mint_to.metadata)
+    }
+
+    // Function to update the image URL of a token whose image is not frozen
+    pub fn set_token_image(env: Env, admin: Address, token_id: i128, image: String) {
+        Self::require_admin(&env, &admin);
+        let (image_frozen, _) = Self::is_metadata_frozen(env.clone(), token_id);
+        assert!(!image_frozen, "Token image is frozen");
+        let mut mint_to: MintTo = Self::token_meta(&env, token_id);
+        mint_to.image = image;
+        env.storage().persistent().set(&DataKey::TokenMeta(token_id), &mint_to);
+    }
+
+    // Function to update the metadata URL of a token whose metadata is not frozen
+    pub fn set_token_metadata(env: Env, admin: Address, token_id: i128, metadata: String) {
+        Self::require_admin(&env, &admin);
+        let (_, metadata_frozen) = Self::is_metadata_frozen(env.clone(), token_id);
+        assert!(!metadata_frozen, "Token metadata is frozen");
+        let mut mint_to: MintTo = Self::token_meta(&env, token_id);
+        mint_to.metadata = metadata;
+        env.storage().persistent().set(&DataKey::TokenMeta(token_id), &mint_to);
+    }
+
+    // Function to permanently freeze the image URL of a token
+    pub fn freeze_image(env: Env, admin: Address, token_id: i128) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().set(&DataKey::ImageFrozen(token_id), &true);
+    }
+
+    // Function to permanently freeze the metadata URL of a token
+    pub fn freeze_metadata(env: Env, admin: Address, token_id: i128) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().set(&DataKey::MetadataFrozen(token_id), &true);
+    }
+
+    // Function to check whether a token's (image, metadata) URLs are frozen
+    pub fn is_metadata_frozen(env: Env, token_id: i128) -> (bool, bool) {
+        let image_frozen = env.storage().persistent().get(&DataKey::ImageFrozen(token_id)).unwrap_or(false);
+        let metadata_frozen = env.storage().persistent().get(&DataKey::MetadataFrozen(token_id)).unwrap_or(false);
+        (image_frozen, metadata_frozen)
+    }
+
+    // Function to reveal a single token once its final metadata is in place
+    pub fn reveal_token(env: Env, admin: Address, token_id: i128) {
+        Self::require_admin(&env, &admin);
+        Self::existing_owner(&env, token_id);
+        env.storage().persistent().set(&ExtDataKey::Revealed(token_id), &true);
+        env.events().publish((symbol_short!("Reveal"),), token_id);
+    }
+
+    // Function to check whether a token has been revealed
+    pub fn is_revealed(env: Env, token_id: i128) -> bool {
+        env.storage().persistent().get(&ExtDataKey::Revealed(token_id)).unwrap_or(false)
+    }
+
+    // Function to list the minted tokens among IDs start..start+limit that still show the placeholder
+    pub fn unrevealed_tokens(env: Env, start: i128, limit: u32) -> Vec<i128> {
+        let mut unrevealed = Vec::new(&env);
+        let end = start.saturating_add(limit as i128).min(Self::SUPPLY + 1);
+        for token_id in start.max(1)..end {
+            if env.storage().persistent().has(&DataKey::Owner(token_id)) && !Self::is_revealed(env.clone(), token_id) {
+                unrevealed.push_back(token_id);
+            }
+        }
+        unrevealed
+    }
+
     // Function to approve an address to manage a specific token
     pub fn approve(env: Env, owner: Address, to: Address, token_id: i128) {
         owner.require_auth();
-        let actual_owner = Self::owner_of(env.clone(), token_id);
+        let actual_owner = Self::existing_owner(&env, token_id);
         if owner == actual_owner {
-            let key = DataKey::Approvals(token_id);
-            let mut approvals = env.storage().persistent().get::<DataKey, Vec<Address>>(&key).unwrap_or_else(|| Vec::new(&env));
-            if !approvals.contains(&to) {
-                approvals.push_back(to.clone());
-                env.storage().persistent().set(&key, &approvals);
-                env.events().publish((symbol_short!("Approval"),), (owner, to, token_id));
-            }
+            Self::set_approval_expiry(&env, &to, token_id, None);
+            Self::grant_approval(&env, owner, to, token_id);
+        } else {
+            panic!("Not the token owner");
+        }
+    }
+
+    // Function to approve an address to manage a specific token until a given timestamp
+    pub fn approve_until(env: Env, owner: Address, to: Address, token_id: i128, expires_at: u64) {
+        owner.require_auth();
+        let actual_owner = Self::existing_owner(&env, token_id);
+        if owner == actual_owner {
+            assert!(expires_at > env.ledger().timestamp(), "Expiry is in the past");
+            Self::set_approval_expiry(&env, &to, token_id, Some(expires_at));
+            Self::grant_approval(&env, owner, to, token_id);
         } else {
             panic!("Not the token owner");
         }
     }
 
-    // Function to transfer a token from one address to another by an approved spender
+    // Function to list operators whose time-limited approval on a token lapses before a given timestamp
+    pub fn approvals_expiring_before(env: Env, token_id: i128, before_ts: u64) -> Vec<Address> {
+        let mut expiring = Vec::new(&env);
+        for (operator, expires_at) in Self::approval_expiries(&env, token_id).iter() {
+            if expires_at < before_ts {
+                expiring.push_back(operator);
+            }
+        }
+        expiring
+    }
+
+    // Function to approve or revoke an operator for all of the caller's tokens
+    pub fn set_approval_for_all(env: Env, owner: Address, operator: Address, approved: bool) {
+        owner.require_auth();
+        let key = DataKey::OperatorApproval(owner.clone(), operator.clone());
+        if approved {
+            env.storage().persistent().set(&key, &true);
+        } else {
+            env.storage().persistent().remove(&key);
+        }
+        env.events().publish((Symbol::new(&env, "ApprovalForAll"),), (owner, operator, approved));
+    }
+
+    // Function to check if an operator is approved for all of an owner's tokens
+    pub fn is_approved_for_all(env: Env, owner: Address, operator: Address) -> bool {
+        env.storage().persistent().get(&DataKey::OperatorApproval(owner, operator)).unwrap_or(false)
+    }
+
+    // Function to blacklist or unblacklist an operator
+    pub fn set_blacklisted(env: Env, admin: Address, operator: Address, blacklisted: bool) {
+        Self::require_admin(&env, &admin);
+        if blacklisted {
+            env.storage().persistent().set(&DataKey::Blacklisted(operator), &true);
+        } else {
+            env.storage().persistent().remove(&DataKey::Blacklisted(operator));
+        }
+    }
+
+    // Function to check if an operator is blacklisted
+    pub fn is_blacklisted(env: Env, operator: Address) -> bool {
+        env.storage().persistent().get(&DataKey::Blacklisted(operator)).unwrap_or(false)
+    }
+
+    // Function to check if an operator is approved for all of an owner's tokens and not blacklisted
+    pub fn effective_operator_approval(env: Env, owner: Address, operator: Address) -> bool {
+        Self::is_approved_for_all(env.clone(), owner, operator.clone()) && !Self::is_blacklisted(env, operator)
+    }
+
+    // Function to list an owner's tokens that an operator can move, per token or through approval for all.
+    // A blacklisted operator can move nothing, whatever its approvals.
+    pub fn my_approved_tokens(env: Env, operator: Address, owner: Address) -> Vec<i128> {
+        if Self::is_blacklisted(env.clone(), operator.clone()) {
+            return Vec::new(&env);
+        }
+        let owned = Self::owned_tokens(&env, &owner);
+        if Self::is_approved_for_all(env.clone(), owner, operator.clone()) {
+            return owned;
+        }
+        let mut approved = Vec::new(&env);
+        for token_id in owned.iter() {
+            if Self::is_approved(env.clone(), operator.clone(), token_id) {
+                approved.push_back(token_id);
+            }
+        }
+        approved
+    }
+
+    // Function to transfer a token from one address to another by an approved spender, unavailable while
+    // royalties are enforced
     pub fn transfer_from(env: Env, spender: Address, from: Address, to: Address, token_id: i128) {
         spender.require_auth();
-        let actual_owner = Self::owner_of(env.clone(), token_id);
+        assert!(!Self::royalties_enforced(env.clone()), "Royalties are enforced, operators must use sell");
+        assert!(!Self::is_blacklisted(env.clone(), spender.clone()), "Operator is blacklisted");
+        let actual_owner = Self::existing_owner(&env, token_id);
         if from != actual_owner {
             panic!("From not owner");
         }
-        let key = DataKey::Approvals(token_id);
-        let approvals = env.storage().persistent().get::<DataKey, Vec<Address>>(&key).unwrap_or_else(|| Vec::new(&env));
-        if !approvals.contains(&spender) {
+        if !Self::is_approved(env.clone(), spender.clone(), token_id)
+            && !Self::is_approved_for_all(env.clone(), from.clone(), spender.clone())
+        {
             panic!("Spender is not approved for this token");
         }
-        env.storage().persistent().set(&DataKey::Owner(token_id), &to);
-        env.storage().persistent().remove(&DataKey::Approvals(token_id));
-        env.events().publish((symbol_short!("Transfer"),), (from, to, token_id));
+        Self::move_token(&env, from, to, token_id);
+    }
+
+    // Function to send several tokens, each to its own recipient, in one all-or-nothing call
+    pub fn scatter(env: Env, owner: Address, transfers: Vec<(Address, i128)>) {
+        owner.require_auth();
+        for (to, token_id) in transfers.iter() {
+            let actual_owner = Self::existing_owner(&env, token_id);
+            if owner != actual_owner {
+                panic!("Not the token owner");
+            }
+            Self::move_token(&env, owner.clone(), to, token_id);
+        }
+    }
+
+    // Function to burn a token, removing it from circulation
+    pub fn burn(env: Env, owner: Address, token_id: i128) {
+        owner.require_auth();
+        let actual_owner = Self::existing_owner(&env, token_id);
+        if owner != actual_owner {
+            panic!("Not the token owner");
+        }
+        Self::burn_token(&env, owner, token_id);
+    }
+
+    // Function to set the refund window after minting and the amount refunded from the treasury
+    pub fn set_refund_policy(env: Env, admin: Address, window: u64, amount: i128) {
+        Self::require_admin(&env, &admin);
+        assert!(amount >= 0, "Refund cannot be negative");
+        env.storage().persistent().set(&DataKey::RefundPolicy, &(window, amount));
+    }
+
+    // Function for the original minter to burn a recently minted token for a partial refund,
+    // never more than was paid for it and always in the token it was paid in. The treasury must
+    // have approved this contract to spend the refund in that token.
+    pub fn burn_for_refund(env: Env, owner: Address, token_id: i128) {
+        owner.require_auth();
+        let actual_owner = Self::existing_owner(&env, token_id);
+        if owner != actual_owner {
+            panic!("Not the token owner");
+        }
+        let (window, amount) = env.storage().persistent().get::<DataKey, (u64, i128)>(&DataKey::RefundPolicy)
+            .expect("Refunds not enabled");
+        let (minter, minted_at) = env.storage().persistent().get::<DataKey, (Address, u64)>(&DataKey::MintedAt(token_id))
+            .expect("Mint record not found for this token");
+        assert_eq!(owner, minter, "Only the original minter can claim a refund");
+        assert!(env.ledger().timestamp() <= minted_at.saturating_add(window), "Refund window has closed");
+        let (payment_token, paid) = env.storage().persistent().get::<ExtDataKey, (Address, i128)>(&ExtDataKey::MintPrice(token_id))
+            .expect("Nothing to refund");
+        let refund = amount.min(paid);
+        assert!(refund > 0, "Nothing to refund");
+
+        Self::burn_token(&env, owner.clone(), token_id);
+
+        let config = Self::sale_config(env.clone()).expect("Sale not configured");
+        token::Client::new(&env, &payment_token)
+            .transfer_from(&env.current_contract_address(), &config.treasury, &owner, &refund);
     }
 
-    // Function to retrieve the NFT associated with a specific address
+    // Function to get the number of tokens burned by an address
+    pub fn burned_by(env: Env, address: Address) -> u32 {
+        env.storage().persistent().get(&DataKey::BurnedBy(address)).unwrap_or(0)
+    }
+
+    // Function to get the number of transfers across the whole collection
+    pub fn total_transfers(env: Env) -> u64 {
+        env.storage().persistent().get(&DataKey::GlobalTransferCount).unwrap_or(0)
+    }
+
+    // Function to get the number of times a token has been transferred
+    pub fn transfer_count(env: Env, token_id: i128) -> u32 {
+        env.storage().persistent().get(&DataKey::TransferCount(token_id)).unwrap_or(0)
+    }
+
+    // Function to list the minted, unburned tokens among IDs start..start+limit that were never transferred
+    pub fn never_transferred(env: Env, start: i128, limit: u32) -> Vec<i128> {
+        let mut untouched = Vec::new(&env);
+        let end = start.saturating_add(limit as i128).min(Self::SUPPLY + 1);
+        for token_id in start.max(1)..end {
+            if env.storage().persistent().has(&DataKey::Owner(token_id))
+                && Self::transfer_count(env.clone(), token_id) == 0
+            {
+                untouched.push_back(token_id);
+            }
+        }
+        untouched
+    }
+
+    // Function to get the most recent owners of a token and when each received it, oldest first,
+    // beginning with the minter until the history fills up
+    pub fn ownership_history(env: Env, token_id: i128) -> Vec<(Address, u64)> {
+        env.storage().persistent().get(&DataKey::History(token_id)).unwrap_or_else(|| Vec::new(&env))
+    }
+
+    // Function to retrieve the NFT associated with a specific address. Tokens the address still holds are
+    // checked first, so reserved tokens above the last public ID are found too; otherwise the public IDs
+    // are scanned. Tokens minted with on-chain metadata have no minting record and are never returned.
     pub fn get_nft_by_address(env: Env, address: Address) -> Option<MintTo> {
-        let token_count: i128 = env.storage().persistent().get(&DataKey::TokenCount).unwrap_or(0);
+        for token_id in Self::owned_tokens(&env, &address).iter() {
+            if let Some(mint_to) = env.storage().persistent().get::<DataKey, MintTo>(&DataKey::TokenMeta(token_id)) {
+                if mint_to.address == address {
+                    return Some(mint_to);
+                }
+            }
+        }
 
+        let token_count: i128 = env.storage().persistent().get(&DataKey::TokenCount).unwrap_or(0);
         for token_id in 1..=token_count {
-            if let Some(mint_to) = env.storage().persistent().get::<DataKey, MintTo>(&DataKey::Approvals(token_id)) {
+            if let Some(mint_to) = env.storage().persistent().get::<DataKey, MintTo>(&DataKey::TokenMeta(token_id)) {
                 if mint_to.address == address {
                     return Some(mint_to);
                 }
@@ -218,6 +780,823 @@ impl StallionNFT {
         }
         None
     }
+
+    // Function to get the token IDs held by an address, always in strictly ascending order
+    pub fn tokens_of_owner(env: Env, owner: Address) -> Vec<i128> {
+        Self::owned_tokens(&env, &owner)
+    }
+
+    // Function to page through the addresses currently holding at least one token. Removing a holder
+    // moves the last holder into its position, so the order is not stable across transfers.
+    pub fn all_holders(env: Env, start: u32, limit: u32) -> Vec<Address> {
+        let holder_count: u32 = env.storage().persistent().get(&ExtDataKey::HolderCount).unwrap_or(0);
+        let mut page = Vec::new(&env);
+        let end = start.saturating_add(limit).min(holder_count);
+        for i in start..end {
+            page.push_back(env.storage().persistent().get(&ExtDataKey::HolderAt(i)).expect("Holder not found"));
+        }
+        page
+    }
+
+    // Function to set the collection-default royalty and whether it is enforced on-chain
+    pub fn set_royalty(env: Env, admin: Address, receiver: Address, bps: u32, enforced: bool) {
+        Self::require_admin(&env, &admin);
+        assert!(bps <= Self::MAX_BPS, "Royalty exceeds 100%");
+        env.storage().persistent().set(&DataKey::Royalty, &Royalty { receiver, bps });
+        env.storage().persistent().set(&DataKey::RoyaltyEnforced, &enforced);
+    }
+
+    // Function to check whether royalties are enforced on-chain, in which case operators can only move
+    // tokens through the royalty-paying sell path rather than transfer_from
+    pub fn royalties_enforced(env: Env) -> bool {
+        env.storage().persistent().get(&DataKey::RoyaltyEnforced).unwrap_or(false)
+    }
+
+    // Function to set a royalty override for a specific token
+    pub fn set_token_royalty(env: Env, admin: Address, token_id: i128, receiver: Address, bps: u32) {
+        Self::require_admin(&env, &admin);
+        assert!(bps <= Self::MAX_BPS, "Royalty exceeds 100%");
+        env.storage().persistent().set(&DataKey::TokenRoyalty(token_id), &Royalty { receiver, bps });
+    }
+
+    // Function to get the royalty in effect for a token, preferring its override over the collection default
+    pub fn effective_royalty(env: Env, token_id: i128) -> (Address, u32) {
+        let royalty = Self::royalty_for(&env, token_id).expect("Royalty not set");
+        (royalty.receiver, royalty.bps)
+    }
+
+    // Function to allow a marketplace to use the royalty-enforcing sell path
+    pub fn add_royalty_marketplace(env: Env, admin: Address, marketplace: Address) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().set(&DataKey::RoyaltyMarketplace(marketplace), &true);
+    }
+
+    // Function to remove a marketplace from the royalty-enforcing allowlist
+    pub fn remove_royalty_marketplace(env: Env, admin: Address, marketplace: Address) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().remove(&DataKey::RoyaltyMarketplace(marketplace));
+    }
+
+    // Function to check whether a marketplace is on the royalty-enforcing allowlist
+    pub fn is_royalty_marketplace(env: Env, marketplace: Address) -> bool {
+        env.storage().persistent().get(&DataKey::RoyaltyMarketplace(marketplace)).unwrap_or(false)
+    }
+
+    // Function for an allowlisted marketplace to sell a token, paying the royalty and the seller from the buyer
+    // in the sale's payment token. The buyer must authorize the purchase.
+    pub fn sell(env: Env, marketplace: Address, seller: Address, buyer: Address, token_id: i128, price: i128) {
+        marketplace.require_auth();
+        buyer.require_auth();
+        assert!(price >= 0, "Price cannot be negative");
+        assert!(Self::is_royalty_marketplace(env.clone(), marketplace.clone()), "Marketplace not allowed");
+        assert!(!Self::is_blacklisted(env.clone(), marketplace.clone()), "Operator is blacklisted");
+        let actual_owner = Self::existing_owner(&env, token_id);
+        if seller != actual_owner {
+            panic!("Not the token owner");
+        }
+        if !Self::is_approved(env.clone(), marketplace.clone(), token_id)
+            && !Self::is_approved_for_all(env.clone(), seller.clone(), marketplace.clone())
+        {
+            panic!("Spender is not approved for this token");
+        }
+
+        let config = Self::sale_config(env.clone()).expect("Sale not configured");
+        let payments = token::Client::new(&env, &config.payment_token);
+        let mut proceeds = price;
+        if let Some(royalty) = Self::royalty_for(&env, token_id) {
+            let royalty_amount = price.checked_mul(royalty.bps as i128)
+                .unwrap_or_else(|| panic_with_error!(&env, Error::PriceOverflow))
+                / Self::MAX_BPS as i128;
+            if royalty_amount > 0 {
+                payments.transfer(&buyer, &royalty.receiver, &royalty_amount);
+                proceeds -= royalty_amount;
+            }
+        }
+        if proceeds > 0 {
+            payments.transfer(&buyer, &seller, &proceeds);
+        }
+
+        Self::move_token(&env, seller, buyer, token_id);
+    }
+
+    // Function to reserve an inclusive range of token IDs from public minting
+    pub fn set_reserved_range(env: Env, admin: Address, start: i128, end: i128) {
+        Self::require_admin(&env, &admin);
+        assert!(start >= 1 && start <= end && end <= Self::SUPPLY, "Invalid reserved range");
+        env.storage().persistent().set(&DataKey::ReservedRange, &(start, end));
+    }
+
+    // Function to get the reserved range of token IDs, if any
+    pub fn reserved_range(env: Env) -> Option<(i128, i128)> {
+        env.storage().persistent().get(&DataKey::ReservedRange)
+    }
+
+    // Function to check whether the next sequential token ID falls in the reserved range
+    pub fn next_is_reserved(env: Env) -> bool {
+        let token_count: i128 = env.storage().persistent().get(&DataKey::TokenCount).unwrap_or(0);
+        Self::is_reserved(&env, token_count + 1)
+    }
+
+    // Function to check whether a token ID could be publicly minted: within supply, past the last public ID,
+    // not reserved, not yet minted and never burned
+    pub fn is_mintable_id(env: Env, token_id: i128) -> bool {
+        let token_count: i128 = env.storage().persistent().get(&DataKey::TokenCount).unwrap_or(0);
+        (1..=Self::SUPPLY).contains(&token_id)
+            && token_id > token_count
+            && !Self::is_reserved(&env, token_id)
+            && !env.storage().persistent().has(&DataKey::Owner(token_id))
+            && !env.storage().persistent().has(&ExtDataKey::Burned(token_id))
+    }
+
+    // Function to get the ID the next public mint would receive, scanning forward from the last public ID
+    // past the reserved range and any IDs already owned; None once the supply is exhausted
+    pub fn next_available_public_id(env: Env) -> Option<i128> {
+        let mut token_id: i128 = env.storage().persistent().get::<DataKey, i128>(&DataKey::TokenCount).unwrap_or(0) + 1;
+        let reserved = Self::reserved_range(env.clone());
+        while token_id <= Self::SUPPLY {
+            match reserved {
+                Some((start, end)) if (start..=end).contains(&token_id) => token_id = end + 1,
+                _ if Self::is_mintable_id(env.clone(), token_id) => return Some(token_id),
+                _ => token_id += 1,
+            }
+        }
+        None
+    }
+
+    // Function to check whether public minting is open right now: not paused, inside the sale and phase
+    // windows, sale switched on, and both the phase cap and overall supply not yet exhausted
+    pub fn minting_live(env: Env) -> bool {
+        if Self::is_paused(env.clone()) {
+            return false;
+        }
+        let now = env.ledger().timestamp();
+        if let Some(config) = Self::sale_config(env.clone()) {
+            if !config.sale_active || !(config.sale_start..=config.sale_end).contains(&now) {
+                return false;
+            }
+        }
+        let phase = Self::current_phase(env.clone());
+        if let Some(info) = env.storage().persistent().get::<DataKey, PhaseInfo>(&DataKey::PhaseConfig(phase)) {
+            if !(info.start..=info.end).contains(&now) {
+                return false;
+            }
+        }
+        if let Some(cap) = Self::phase_cap(env.clone(), phase) {
+            if Self::phase_minted(env.clone(), phase) >= cap {
+                return false;
+            }
+        }
+        Self::next_available_public_id(env).is_some()
+    }
+
+    // Function to set the active sale phase
+    pub fn set_current_phase(env: Env, admin: Address, phase: u32) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().set(&DataKey::CurrentPhase, &phase);
+    }
+
+    // Function to get the active sale phase
+    pub fn current_phase(env: Env) -> u32 {
+        env.storage().persistent().get(&DataKey::CurrentPhase).unwrap_or(0)
+    }
+
+    // Function to cap the number of tokens mintable during a phase
+    pub fn set_phase_cap(env: Env, admin: Address, phase: u32, cap: u32) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().set(&DataKey::PhaseCap(phase), &cap);
+    }
+
+    // Function to get the mint cap of a phase, if any
+    pub fn phase_cap(env: Env, phase: u32) -> Option<u32> {
+        env.storage().persistent().get(&DataKey::PhaseCap(phase))
+    }
+
+    // Function to configure a phase's cap, window and whitelist gating
+    pub fn configure_phase(env: Env, admin: Address, phase: u32, info: PhaseInfo) {
+        Self::require_admin(&env, &admin);
+        assert!(info.start < info.end, "Phase window start must precede its end");
+
+        let key = DataKey::PhaseConfig(phase);
+        if !env.storage().persistent().has(&key) {
+            let phase_count = Self::phase_count(env.clone());
+            env.storage().persistent().set(&DataKey::PhaseCount, &(phase_count + 1));
+        }
+        match info.cap {
+            Some(cap) => env.storage().persistent().set(&DataKey::PhaseCap(phase), &cap),
+            None => env.storage().persistent().remove(&DataKey::PhaseCap(phase)),
+        }
+        env.storage().persistent().set(&key, &info);
+    }
+
+    // Function to get the number of distinct phases configured with configure_phase
+    pub fn phase_count(env: Env) -> u32 {
+        env.storage().persistent().get(&DataKey::PhaseCount).unwrap_or(0)
+    }
+
+    // Function to get the configuration of a phase
+    pub fn phase_info(env: Env, phase: u32) -> PhaseInfo {
+        let mut info: PhaseInfo = env.storage().persistent().get(&DataKey::PhaseConfig(phase))
+            .expect("Phase not configured");
+        // The cap may have been changed since with set_phase_cap
+        info.cap = Self::phase_cap(env, phase);
+        info
+    }
+
+    // Function to get the number of tokens minted during a phase
+    pub fn phase_minted(env: Env, phase: u32) -> u32 {
+        env.storage().persistent().get(&DataKey::PhaseMinted(phase)).unwrap_or(0)
+    }
+
+    // Function to cap the number of tokens a single address may hold
+    pub fn set_holding_cap(env: Env, admin: Address, cap: u32) {
+        Self::require_admin(&env, &admin);
+        assert!(cap > 0, "Holding cap must be positive");
+        env.storage().persistent().set(&DataKey::HoldingCap, &cap);
+    }
+
+    // Function to get the holding cap, if any
+    pub fn holding_cap(env: Env) -> Option<u32> {
+        env.storage().persistent().get(&DataKey::HoldingCap)
+    }
+
+    // Function to exempt an address (e.g. treasury or marketplace) from the holding and mint caps
+    pub fn set_exempt(env: Env, admin: Address, address: Address, exempt: bool) {
+        Self::require_admin(&env, &admin);
+        if exempt {
+            env.storage().persistent().set(&DataKey::Exempt(address), &true);
+        } else {
+            env.storage().persistent().remove(&DataKey::Exempt(address));
+        }
+    }
+
+    // Function to check whether an address is exempt from the holding and mint caps
+    pub fn is_exempt(env: Env, address: Address) -> bool {
+        env.storage().persistent().get(&DataKey::Exempt(address)).unwrap_or(false)
+    }
+
+    // Function to set the number of public mints allowed per address, one by default
+    pub fn set_mint_cap(env: Env, admin: Address, cap: u32) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().set(&DataKey::MintCap, &cap);
+    }
+
+    // Function to set or clear an address's override of the mint cap
+    pub fn set_mint_cap_override(env: Env, admin: Address, address: Address, cap: Option<u32>) {
+        Self::require_admin(&env, &admin);
+        match cap {
+            Some(cap) => env.storage().persistent().set(&DataKey::MintCapOverride(address), &cap),
+            None => env.storage().persistent().remove(&DataKey::MintCapOverride(address)),
+        }
+    }
+
+    // Function to get the mint cap that applies to an address: unlimited if exempt, else its override, else the global cap
+    pub fn effective_cap(env: Env, address: Address) -> u32 {
+        if Self::is_exempt(env.clone(), address.clone()) {
+            return u32::MAX;
+        }
+        env.storage().persistent().get(&DataKey::MintCapOverride(address))
+            .or_else(|| env.storage().persistent().get(&DataKey::MintCap))
+            .unwrap_or(1)
+    }
+
+    // Function to get the receipt of an address's most recent public mint, if it has minted
+    pub fn last_mint_receipt(env: Env, address: Address) -> Option<MintReceipt> {
+        env.storage().persistent().get(&DataKey::LastMintReceipt(address))
+    }
+
+    // Function to get the number of public mints made by an address
+    pub fn mint_count(env: Env, address: Address) -> u32 {
+        env.storage().persistent().get(&DataKey::MintCount(address)).unwrap_or(0)
+    }
+
+    // Function to apply the whole sale configuration in one call. The royalty's enforcement flag is
+    // left as it is; change it through set_royalty.
+    pub fn configure(env: Env, admin: Address, config: SaleConfig) {
+        Self::require_admin(&env, &admin);
+        assert!(config.price >= 0, "Price cannot be negative");
+        assert!(config.sale_start < config.sale_end, "Sale window start must precede its end");
+        assert!(config.royalty.bps <= Self::MAX_BPS, "Royalty exceeds 100%");
+
+        match config.holding_cap {
+            Some(cap) => {
+                assert!(cap > 0, "Holding cap must be positive");
+                env.storage().persistent().set(&DataKey::HoldingCap, &cap);
+            }
+            None => env.storage().persistent().remove(&DataKey::HoldingCap),
+        }
+        env.storage().persistent().set(&DataKey::Royalty, &config.royalty);
+        env.storage().persistent().set(&DataKey::SaleConfig, &config);
+    }
+
+    // Function to get the sale configuration, if any
+    pub fn sale_config(env: Env) -> Option<SaleConfig> {
+        env.storage().persistent().get(&DataKey::SaleConfig)
+    }
+
+    // Function to let whitelisted addresses mint for free between two timestamps
+    pub fn set_free_mint_window(env: Env, admin: Address, start: u64, end: u64) {
+        Self::require_admin(&env, &admin);
+        assert!(start < end, "Free mint window start must precede its end");
+        env.storage().persistent().set(&DataKey::FreeMintWindow, &(start, end));
+    }
+
+    // Function to get the payment token and amount an address would pay to mint one token right now
+    pub fn mint_quote(env: Env, address: Address) -> (Address, i128) {
+        let config = Self::sale_config(env.clone()).expect("Sale not configured");
+        if let Some((start, end)) = env.storage().persistent().get::<DataKey, (u64, u64)>(&DataKey::FreeMintWindow) {
+            let now = env.ledger().timestamp();
+            if (start..=end).contains(&now) && Self::get_whitelist(env.clone()).contains(&address) {
+                return (config.payment_token, 0);
+            }
+        }
+        (config.payment_token, config.price)
+    }
+
+    // Function to mint a reserved token ID to any address, including the contract itself as treasury
+    pub fn mint_reserved(env: Env, admin: Address, to: Address, token_id: i128) {
+        Self::require_admin(&env, &admin);
+        assert!(!Self::is_reserve_locked(env.clone()), "Reserve is locked");
+        assert!(Self::is_reserved(&env, token_id), "Token ID is not reserved");
+        assert!(!env.storage().persistent().has(&DataKey::Owner(token_id)), "Token already minted");
+        assert!(!env.storage().persistent().has(&ExtDataKey::Burned(token_id)), "Token was burned");
+
+        Self::store_default_meta(&env, to.clone(), token_id);
+        env.storage().persistent().set(&DataKey::Owner(token_id), &to);
+        Self::add_owned_token(&env, &to, token_id);
+        Self::record_history(&env, token_id, &to);
+
+        env.events().publish((symbol_short!("Mint"),), (to, token_id));
+    }
+
+    // Function to permanently stop any further reserved minting; public minting is unaffected
+    pub fn lock_reserve(env: Env, admin: Address) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().set(&ExtDataKey::ReserveLocked, &true);
+    }
+
+    // Function to check whether reserved minting has been locked
+    pub fn is_reserve_locked(env: Env) -> bool {
+        env.storage().persistent().get(&ExtDataKey::ReserveLocked).unwrap_or(false)
+    }
+
+    // Function to list the reserved token IDs still held by the contract's treasury
+    pub fn reserved_in_treasury(env: Env) -> Vec<i128> {
+        let mut reserved = Vec::new(&env);
+        for token_id in Self::owned_tokens(&env, &env.current_contract_address()).iter() {
+            if Self::is_reserved(&env, token_id) {
+                reserved.push_back(token_id);
+            }
+        }
+        reserved
+    }
+
+    // Function to compare the number of configured reserved IDs with the reserved tokens the admin still holds
+    pub fn reserved_reconciliation(env: Env) -> (i128, i128) {
+        let configured = match Self::reserved_range(env.clone()) {
+            Some((start, end)) => end - start + 1,
+            None => 0,
+        };
+        let mut held: i128 = 0;
+        for token_id in Self::owned_tokens(&env, &Self::admin(&env)).iter() {
+            if Self::is_reserved(&env, token_id) {
+                held += 1;
+            }
+        }
+        (configured, held)
+    }
+
+    // Function to hand a reserved token held by the contract's treasury to a recipient
+    pub fn release_reserved(env: Env, admin: Address, token_id: i128, to: Address) {
+        Self::require_admin(&env, &admin);
+        assert!(Self::is_reserved(&env, token_id), "Token ID is not reserved");
+        let treasury = env.current_contract_address();
+        assert_eq!(Self::existing_owner(&env, token_id), treasury, "Token is not held by the treasury");
+        Self::move_token(&env, treasury, to, token_id);
+    }
+
+    // Function to pause public minting
+    pub fn pause(env: Env, admin: Address) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().set(&DataKey::Paused, &true);
+    }
+
+    // Function to resume public minting, also resetting the circuit breaker's window
+    pub fn unpause(env: Env, admin: Address) {
+        Self::require_admin(&env, &admin);
+        env.storage().persistent().set(&DataKey::Paused, &false);
+        env.storage().persistent().remove(&DataKey::RecentMints);
+    }
+
+    // Function to check whether public minting is paused
+    pub fn is_paused(env: Env) -> bool {
+        env.storage().persistent().get(&DataKey::Paused).unwrap_or(false)
+    }
+
+    // Function to auto-pause minting when more than max_mints happen within window seconds
+    pub fn set_mint_rate_limit(env: Env, admin: Address, max_mints: u32, window: u64) {
+        Self::require_admin(&env, &admin);
+        assert!(max_mints > 0 && max_mints <= Self::MAX_RATE_LIMIT_MINTS && window > 0, "Invalid mint rate limit");
+        env.storage().persistent().set(&DataKey::MintRateLimit, &(max_mints, window));
+    }
+
+    // Function to get the nonce the next permit signed by an owner must use
+    pub fn permit_nonce(env: Env, owner: Address) -> u64 {
+        env.storage().persistent().get(&DataKey::PermitNonce(owner)).unwrap_or(0)
+    }
+
+    // Function to record an approval the token owner authorized off-chain and anyone may submit.
+    // The owner signs an authorization entry over (operator, token_id, deadline, nonce), so the account's
+    // own signers and thresholds, or a contract wallet's checks, decide whether the permit is valid.
+    pub fn permit(env: Env, owner: Address, operator: Address, token_id: i128, deadline: u64) {
+        assert!(env.ledger().timestamp() <= deadline, "Permit expired");
+        let actual_owner = Self::existing_owner(&env, token_id);
+        assert_eq!(owner, actual_owner, "Not the token owner");
+
+        let nonce = Self::permit_nonce(env.clone(), owner.clone());
+        owner.require_auth_for_args((operator.clone(), token_id, deadline, nonce).into_val(&env));
+        env.storage().persistent().set(&DataKey::PermitNonce(owner.clone()), &(nonce + 1));
+
+        Self::set_approval_expiry(&env, &operator, token_id, None);
+        Self::grant_approval(&env, owner, operator, token_id);
+    }
+}
+
+// Test-only entry points for integration harnesses, never compiled into release builds
+#[cfg(feature = "testutils")]
+#[contractimpl]
+impl StallionNFT {
+    // Function to seed a token with the given ownership and metadata, skipping auth and whitelist checks
+    pub fn test_seed_mint(env: Env, to: Address, token_id: i128, metadata: String, image: String) {
+        assert!(!env.storage().persistent().has(&DataKey::Owner(token_id)), "Token already minted");
+        assert!(!env.storage().persistent().has(&ExtDataKey::Burned(token_id)), "Token was burned");
+        let mint_to = MintTo {
+            address: to.clone(),
+            token_id,
+            metadata,
+            image,
+        };
+        env.storage().persistent().set(&DataKey::TokenMeta(token_id), &mint_to);
+        env.storage().persistent().set(&DataKey::Owner(token_id), &to);
+        Self::add_owned_token(&env, &to, token_id);
+        Self::record_history(&env, token_id, &to);
+    }
+}
+
+// Internal helpers, not exposed as contract functions
+impl StallionNFT {
+    // Require the caller's authorization and that they are the stored admin
+    fn require_admin(env: &Env, admin: &Address) {
+        admin.require_auth();
+        assert_eq!(*admin, Self::admin(env), "Caller is not the admin");
+    }
+
+    // Get the stored admin address
+    fn admin(env: &Env) -> Address {
+        env.storage().instance().get(&DataKey::Admin).expect("Admin address not set")
+    }
+
+    // Store a token's minting record with the collection's default metadata and image
+    fn store_default_meta(env: &Env, to: Address, token_id: i128) {
+        let mint_to = MintTo {
+            address: to,
+            token_id,
+            metadata: Self::token_uri(env.clone()),
+            image: Self::token_image(env.clone()),
+        };
+        env.storage().persistent().set(&DataKey::TokenMeta(token_id), &mint_to);
+    }
+
+    // Add an operator to a token's approvals, emitting an event if it was not already approved
+    fn grant_approval(env: &Env, owner: Address, operator: Address, token_id: i128) {
+        let key = DataKey::Approvals(token_id);
+        let mut approvals = env.storage().persistent().get::<DataKey, Vec<Address>>(&key).unwrap_or_else(|| Vec::new(env));
+        if !approvals.contains(&operator) {
+            if approvals.len() >= Self::MAX_APPROVALS {
+                approvals = Self::prune_expired_approvals(env, token_id, approvals);
+            }
+            assert!(approvals.len() < Self::MAX_APPROVALS, "Too many approvals");
+            approvals.push_back(operator.clone());
+            env.storage().persistent().set(&key, &approvals);
+            env.events().publish((symbol_short!("Approval"),), (owner, operator, token_id));
+        }
+    }
+
+    // Run the public mint checks and assign the next token ID to a whitelisted address.
+    // `allowlisted` is set when eligibility was already proven another way, e.g. by a merkle proof.
+    fn mint_public(env: &Env, to: &Address, allowlisted: bool) -> i128 {
+        to.require_auth();
+        assert!(!Self::is_paused(env.clone()), "Contract is paused");
+
+        let sale_config = Self::sale_config(env.clone());
+        if let Some(config) = &sale_config {
+            assert!(config.sale_active, "Sale is not active");
+            let now = env.ledger().timestamp();
+            assert!((config.sale_start..=config.sale_end).contains(&now), "Outside the sale window");
+        }
+
+        let phase = Self::current_phase(env.clone());
+        let phase_config = env.storage().persistent().get::<DataKey, PhaseInfo>(&DataKey::PhaseConfig(phase));
+        if let Some(info) = &phase_config {
+            let now = env.ledger().timestamp();
+            assert!((info.start..=info.end).contains(&now), "Outside the phase window");
+        }
+
+        // Phases configured as open skip the whitelist; otherwise the whitelist mode decides the gate
+        if !allowlisted && phase_config.is_none_or(|info| info.whitelist_gated) {
+            match Self::whitelist_mode(env.clone()) {
+                Self::WHITELIST_OPEN => {}
+                Self::WHITELIST_VECTOR => {
+                    let whitelist = env.storage().persistent().get::<DataKey, Vec<Address>>(&DataKey::Whitelist)
+                        .expect("Whitelist not found");
+                    assert!(whitelist.contains(to), "Address not whitelisted");
+                }
+                Self::WHITELIST_MERKLE => panic!("Merkle proof required"),
+                _ => panic!("Mint signature required"),
+            }
+        }
+
+        // Check the address has not used up its mint cap
+        let mint_count = Self::mint_count(env.clone(), to.clone());
+        assert!(mint_count < Self::effective_cap(env.clone(), to.clone()), "Address has reached its mint cap");
+
+        let token_count = Self::next_available_public_id(env.clone()).expect("Maximum token supply reached");
+
+        // Count the mint against the active phase, enforcing its cap if one is set
+        let phase_minted = Self::phase_minted(env.clone(), phase);
+        if let Some(cap) = Self::phase_cap(env.clone(), phase) {
+            assert!(phase_minted < cap, "Phase cap reached");
+        }
+        env.storage().persistent().set(&DataKey::PhaseMinted(phase), &(phase_minted + 1));
+
+        env.storage().persistent().set(&DataKey::TokenCount, &token_count);
+        env.storage().persistent().set(&DataKey::Owner(token_count), to);
+        Self::add_owned_token(env, to, token_count);
+        Self::record_history(env, token_count, to);
+
+        // Mark the address as having minted a token
+        env.storage().persistent().set(&DataKey::HasMinted(to.clone()), &true);
+        env.storage().persistent().set(&DataKey::MintCount(to.clone()), &(mint_count + 1));
+        env.storage().persistent().set(&DataKey::LastMintTime, &env.ledger().timestamp());
+        env.storage().persistent().set(&DataKey::MintedAt(token_count), &(to.clone(), env.ledger().timestamp()));
+
+        let mut receipt = MintReceipt {
+            token_id: token_count,
+            timestamp: env.ledger().timestamp(),
+            phase,
+            payment_token: None,
+            price: 0,
+        };
+        if let Some(config) = sale_config {
+            let (payment_token, price) = Self::mint_quote(env.clone(), to.clone());
+            if price > 0 {
+                token::Client::new(env, &payment_token).transfer(to, &config.treasury, &price);
+                env.storage().persistent().set(&ExtDataKey::MintPrice(token_count), &(payment_token.clone(), price));
+            }
+            receipt.payment_token = Some(payment_token);
+            receipt.price = price;
+        }
+        env.storage().persistent().set(&DataKey::LastMintReceipt(to.clone()), &receipt);
+
+        env.events().publish((symbol_short!("Mint"),), (to.clone(), token_count));
+        Self::record_mint_velocity(env);
+        token_count
+    }
+
+    // Track mints inside the sliding window and pause minting once they exceed the configured rate.
+    // The mint that trips the breaker still succeeds, since panicking would also undo the pause.
+    fn record_mint_velocity(env: &Env) {
+        let (max_mints, window) = match env.storage().persistent().get::<DataKey, (u32, u64)>(&DataKey::MintRateLimit) {
+            Some(limit) => limit,
+            None => return,
+        };
+        let now = env.ledger().timestamp();
+        let recent = env.storage().persistent().get::<DataKey, Vec<u64>>(&DataKey::RecentMints).unwrap_or_else(|| Vec::new(env));
+        let mut in_window = Vec::new(env);
+        for minted_at in recent.iter() {
+            if now - minted_at < window {
+                in_window.push_back(minted_at);
+            }
+        }
+        in_window.push_back(now);
+        env.storage().persistent().set(&DataKey::RecentMints, &in_window);
+
+        if in_window.len() > max_mints {
+            env.storage().persistent().set(&DataKey::Paused, &true);
+            env.events().publish((symbol_short!("AutoPause"),), (in_window.len(), window));
+        }
+    }
+
+    // Fold a merkle proof over an address's leaf, hashing each pair in sorted order.
+    // Leaves are sha256 of the address XDR.
+    fn merkle_root_of(env: &Env, address: &Address, proof: &Vec<BytesN<32>>) -> BytesN<32> {
+        let mut node: BytesN<32> = env.crypto().sha256(&address.clone().to_xdr(env)).into();
+        for sibling in proof.iter() {
+            let (first, second) = if node <= sibling { (node, sibling) } else { (sibling, node) };
+            let mut pair = Bytes::new(env);
+            pair.extend_from_array(&first.to_array());
+            pair.extend_from_array(&second.to_array());
+            node = env.crypto().sha256(&pair).into();
+        }
+        node
+    }
+
+    // Check that on-chain metadata text is short, valid UTF-8 and needs no escaping inside a JSON string
+    fn check_onchain_text(text: &String) {
+        let len = text.len() as usize;
+        assert!(len <= Self::MAX_ATTRIBUTE_LEN, "On-chain metadata text too long");
+        let mut buf = [0u8; Self::MAX_ATTRIBUTE_LEN];
+        text.copy_into_slice(&mut buf[..len]);
+        assert!(core::str::from_utf8(&buf[..len]).is_ok(), "On-chain metadata is not valid UTF-8");
+        assert!(buf[..len].iter().all(|b| *b >= 0x20 && *b != b'"' && *b != b'\\'), "Invalid character in on-chain metadata");
+    }
+
+    // Append raw bytes to a JSON buffer
+    fn push_bytes(buf: &mut [u8], len: &mut usize, bytes: &[u8]) {
+        buf[*len..*len + bytes.len()].copy_from_slice(bytes);
+        *len += bytes.len();
+    }
+
+    // Append the contents of a String to a JSON buffer
+    fn push_string(buf: &mut [u8], len: &mut usize, text: &String) {
+        let text_len = text.len() as usize;
+        text.copy_into_slice(&mut buf[*len..*len + text_len]);
+        *len += text_len;
+    }
+
+    // Check whether a token ID lies in the reserved range
+    fn is_reserved(env: &Env, token_id: i128) -> bool {
+        match Self::reserved_range(env.clone()) {
+            Some((start, end)) => (start..=end).contains(&token_id),
+            None => false,
+        }
+    }
+
+    // Get the minting record of a token, failing with OnchainMetadata for tokens minted through mint_onchain
+    fn token_meta(env: &Env, token_id: i128) -> MintTo {
+        if let Some(mint_to) = env.storage().persistent().get(&DataKey::TokenMeta(token_id)) {
+            return mint_to;
+        }
+        if env.storage().persistent().has(&DataKey::OnchainMeta(token_id)) {
+            panic_with_error!(env, Error::OnchainMetadata);
+        }
+        panic!("MintTo struct not found for this token");
+    }
+
+    // Get the owner of a token, failing with TokenNotFound if it was never minted or has been burned
+    fn existing_owner(env: &Env, token_id: i128) -> Address {
+        env.storage().persistent().get(&DataKey::Owner(token_id))
+            .unwrap_or_else(|| panic_with_error!(env, Error::TokenNotFound))
+    }
+
+    // Remove a token from circulation and credit the burn to its owner
+    fn burn_token(env: &Env, owner: Address, token_id: i128) {
+        env.storage().persistent().remove(&DataKey::Owner(token_id));
+        env.storage().persistent().remove(&DataKey::TokenMeta(token_id));
+        env.storage().persistent().remove(&DataKey::OnchainMeta(token_id));
+        env.storage().persistent().remove(&DataKey::TokenSoulbound(token_id));
+        env.storage().persistent().remove(&ExtDataKey::Revealed(token_id));
+        env.storage().persistent().remove(&ExtDataKey::MintPrice(token_id));
+        env.storage().persistent().set(&ExtDataKey::Burned(token_id), &true);
+        Self::clear_approvals(env, token_id);
+        Self::remove_owned_token(env, &owner, token_id);
+
+        let burned = Self::burned_by(env.clone(), owner.clone());
+        env.storage().persistent().set(&DataKey::BurnedBy(owner.clone()), &(burned + 1));
+
+        env.events().publish((symbol_short!("Burn"),), (owner, token_id));
+    }
+
+    // Hand a token to a new owner, clearing its approvals and updating the ownership indexes
+    fn move_token(env: &Env, from: Address, to: Address, token_id: i128) {
+        assert!(!Self::is_soulbound(env.clone(), token_id), "Token is soulbound");
+        env.storage().persistent().set(&DataKey::Owner(token_id), &to);
+        Self::clear_approvals(env, token_id);
+        Self::remove_owned_token(env, &from, token_id);
+        Self::add_owned_token(env, &to, token_id);
+
+        let transfers = Self::total_transfers(env.clone());
+        env.storage().persistent().set(&DataKey::GlobalTransferCount, &(transfers + 1));
+        let token_transfers = Self::transfer_count(env.clone(), token_id);
+        env.storage().persistent().set(&DataKey::TransferCount(token_id), &(token_transfers + 1));
+
+        Self::record_history(env, token_id, &to);
+
+        env.events().publish((symbol_short!("Transfer"),), (from, to, token_id));
+    }
+
+    // Append a new owner to a token's history, dropping the oldest entry once it is full
+    fn record_history(env: &Env, token_id: i128, owner: &Address) {
+        let mut history = Self::ownership_history(env.clone(), token_id);
+        if history.len() >= Self::MAX_HISTORY {
+            history.pop_front();
+        }
+        history.push_back((owner.clone(), env.ledger().timestamp()));
+        env.storage().persistent().set(&DataKey::History(token_id), &history);
+    }
+
+    // Get the royalty in effect for a token, preferring its override over the collection default
+    fn royalty_for(env: &Env, token_id: i128) -> Option<Royalty> {
+        env.storage().persistent().get::<DataKey, Royalty>(&DataKey::TokenRoyalty(token_id))
+            .or_else(|| env.storage().persistent().get::<DataKey, Royalty>(&DataKey::Royalty))
+    }
+
+    // Drop approvals whose expiry has passed from a token's approval list, so they stop counting toward the cap
+    fn prune_expired_approvals(env: &Env, token_id: i128, approvals: Vec<Address>) -> Vec<Address> {
+        let now = env.ledger().timestamp();
+        let mut expiries = Self::approval_expiries(env, token_id);
+        let mut kept = Vec::new(env);
+        for operator in approvals.iter() {
+            match expiries.get(operator.clone()) {
+                Some(expires_at) if now > expires_at => {
+                    expiries.remove(operator);
+                }
+                _ => kept.push_back(operator),
+            }
+        }
+        env.storage().persistent().set(&DataKey::ApprovalExpiry(token_id), &expiries);
+        kept
+    }
+
+    // Get the expiry times of the time-limited approvals on a token
+    fn approval_expiries(env: &Env, token_id: i128) -> Map<Address, u64> {
+        env.storage().persistent().get(&DataKey::ApprovalExpiry(token_id)).unwrap_or_else(|| Map::new(env))
+    }
+
+    // Set or clear the expiry time of an operator's approval on a token
+    fn set_approval_expiry(env: &Env, operator: &Address, token_id: i128, expires_at: Option<u64>) {
+        let mut expiries = Self::approval_expiries(env, token_id);
+        match expires_at {
+            Some(expires_at) => expiries.set(operator.clone(), expires_at),
+            None if expiries.contains_key(operator.clone()) => {
+                expiries.remove(operator.clone());
+            }
+            None => return,
+        }
+        env.storage().persistent().set(&DataKey::ApprovalExpiry(token_id), &expiries);
+    }
+
+    // Drop every per-token approval, used whenever a token changes hands.
+    // Whole entries are removed without being read, so the cost does not grow with the approval count;
+    // operator-for-all approvals belong to the owner rather than the token and are left alone.
+    fn clear_approvals(env: &Env, token_id: i128) {
+        env.storage().persistent().remove(&DataKey::Approvals(token_id));
+        env.storage().persistent().remove(&DataKey::ApprovalExpiry(token_id));
+    }
+
+    // Get the token IDs held by an address
+    fn owned_tokens(env: &Env, owner: &Address) -> Vec<i128> {
+        env.storage().persistent().get(&DataKey::OwnedTokens(owner.clone())).unwrap_or_else(|| Vec::new(env))
+    }
+
+    // Record a token under its owner in ascending ID order, adding the owner to the holder set on their first token
+    fn add_owned_token(env: &Env, owner: &Address, token_id: i128) {
+        let mut owned = Self::owned_tokens(env, owner);
+        if let Some(cap) = Self::holding_cap(env.clone()) {
+            assert!(owned.len() < cap || Self::is_exempt(env.clone(), owner.clone()), "Holding cap reached");
+        }
+        if owned.is_empty() {
+            let holder_count: u32 = env.storage().persistent().get(&ExtDataKey::HolderCount).unwrap_or(0);
+            env.storage().persistent().set(&ExtDataKey::HolderAt(holder_count), owner);
+            env.storage().persistent().set(&ExtDataKey::HolderIndex(owner.clone()), &holder_count);
+            env.storage().persistent().set(&ExtDataKey::HolderCount, &(holder_count + 1));
+        }
+        // Keep the index in ascending order so galleries get a stable ordering
+        if let Err(pos) = owned.binary_search(token_id) {
+            owned.insert(pos, token_id);
+        }
+        env.storage().persistent().set(&DataKey::OwnedTokens(owner.clone()), &owned);
+    }
+
+    // Drop a token from its owner, removing the owner from the holder set when their last token leaves
+    fn remove_owned_token(env: &Env, owner: &Address, token_id: i128) {
+        let key = DataKey::OwnedTokens(owner.clone());
+        let mut owned = Self::owned_tokens(env, owner);
+        if let Ok(pos) = owned.binary_search(token_id) {
+            owned.remove(pos);
+        }
+        if owned.is_empty() {
+            env.storage().persistent().remove(&key);
+            Self::remove_holder(env, owner);
+        } else {
+            env.storage().persistent().set(&key, &owned);
+        }
+    }
+
+    // Drop an address from the holder set, moving the last holder into its position
+    fn remove_holder(env: &Env, owner: &Address) {
+        let index_key = ExtDataKey::HolderIndex(owner.clone());
+        if let Some(pos) = env.storage().persistent().get::<ExtDataKey, u32>(&index_key) {
+            let last: u32 = env.storage().persistent().get::<ExtDataKey, u32>(&ExtDataKey::HolderCount).unwrap_or(0) - 1;
+            if pos != last {
+                let moved: Address = env.storage().persistent().get(&ExtDataKey::HolderAt(last)).expect("Holder not found");
+                env.storage().persistent().set(&ExtDataKey::HolderAt(pos), &moved);
+                env.storage().persistent().set(&ExtDataKey::HolderIndex(moved), &pos);
+            }
+            env.storage().persistent().remove(&ExtDataKey::HolderAt(last));
+            env.storage().persistent().remove(&index_key);
+            env.storage().persistent().set(&ExtDataKey::HolderCount, &last);
+        }
+    }
 }
 
 mod test;
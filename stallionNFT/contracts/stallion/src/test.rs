@@ -1,21 +1,1831 @@
 #![cfg(test)]
 
+extern crate std;
+
 use super::*;
-use soroban_sdk::{vec, Env, String};
+use ed25519_dalek::{Signer, SigningKey};
+use soroban_sdk::{
+    testutils::{Address as _, Events, Ledger, MockAuth, MockAuthInvoke},
+    token::{self, StellarAssetClient},
+    vec, Address, BytesN, Env, IntoVal, String, Symbol,
+};
+
+fn setup<'a>(env: &Env) -> (StallionNFTClient<'a>, Address) {
+    let admin = Address::generate(env);
+    let contract_id = env.register_contract(None, StallionNFT);
+    let client = StallionNFTClient::new(env, &contract_id);
+    client.initialize(&admin);
+    (client, admin)
+}
+
+#[test]
+fn test_initialize() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+    assert!(env.auths().iter().any(|(address, _)| *address == admin));
+
+    // The admin can only be set once
+    assert!(client.try_initialize(&Address::generate(&env)).is_err());
+
+    let alice = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    assert!(client.try_remove_from_whitelist(&Address::generate(&env), &alice).is_err());
+    client.remove_from_whitelist(&admin, &alice);
+    assert!(client.get_whitelist().is_empty());
+}
+
+#[test]
+fn test_initialize_requires_admin_auth() {
+    let env = Env::default();
+    let contract_id = env.register_contract(None, StallionNFT);
+    let client = StallionNFTClient::new(&env, &contract_id);
+    assert!(client.try_initialize(&Address::generate(&env)).is_err());
+}
+
+#[test]
+fn test_approve_keeps_token_meta() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let operator = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+
+    // Approvals and the minting record live under separate keys
+    client.approve(&alice, &operator, &1);
+    assert!(client.is_approved(&operator, &1));
+    assert_eq!(client.get_token_image(&1), client.token_image());
+}
+
+#[test]
+fn test_all_holders() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let carol = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.add_to_whitelist(&admin, &bob);
+
+    client.mint(&alice);
+    client.mint(&bob);
+    assert_eq!(client.all_holders(&0, &10), vec![&env, alice.clone(), bob.clone()]);
+
+    // Alice's only token moves to Carol, so Alice leaves the set and Carol joins it
+    client.transfer(&alice, &carol, &1);
+    assert_eq!(client.all_holders(&0, &10), vec![&env, bob.clone(), carol.clone()]);
+    assert_eq!(client.all_holders(&0, &1), vec![&env, bob.clone()]);
+    assert_eq!(client.all_holders(&1, &1), vec![&env, carol.clone()]);
+    assert_eq!(client.all_holders(&2, &1), vec![&env]);
+
+    // Removing a holder from the front moves the last holder into its place
+    client.transfer(&bob, &carol, &2);
+    assert_eq!(client.all_holders(&0, &10), vec![&env, carol.clone()]);
+    client.transfer(&carol, &alice, &1);
+    assert_eq!(client.all_holders(&0, &10), vec![&env, carol.clone(), alice.clone()]);
+    client.transfer(&carol, &bob, &2);
+    assert_eq!(client.all_holders(&0, &10), vec![&env, alice, bob]);
+}
+
+#[test]
+fn test_effective_royalty() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let artist = Address::generate(&env);
+    let collaborator = Address::generate(&env);
+    client.set_royalty(&admin, &artist, &500, &false);
+    client.set_token_royalty(&admin, &2, &collaborator, &750);
+
+    assert_eq!(client.effective_royalty(&1), (artist, 500));
+    assert_eq!(client.effective_royalty(&2), (collaborator, 750));
+}
+
+fn mock_permit(
+    env: &Env,
+    client: &StallionNFTClient,
+    owner: &Address,
+    operator: &Address,
+    deadline: u64,
+    nonce: u64,
+) {
+    env.mock_auths(&[MockAuth {
+        address: owner,
+        invoke: &MockAuthInvoke {
+            contract: &client.address,
+            fn_name: "permit",
+            args: (operator.clone(), 1_i128, deadline, nonce).into_val(env),
+            sub_invokes: &[],
+        },
+    }]);
+}
+
+#[test]
+fn test_permit() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let owner = Address::generate(&env);
+    let operator = Address::generate(&env);
+    client.add_to_whitelist(&admin, &owner);
+    client.mint(&owner);
+
+    // Only the owner's authorization over the permit arguments is needed
+    mock_permit(&env, &client, &owner, &operator, 100, 0);
+    client.permit(&owner, &operator, &1, &100);
+
+    assert!(client.is_approved(&operator, &1));
+    assert_eq!(client.permit_nonce(&owner), 1);
+}
+
+#[test]
+fn test_permit_replaces_expired_approval() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let owner = Address::generate(&env);
+    let operator = Address::generate(&env);
+    client.add_to_whitelist(&admin, &owner);
+    client.mint(&owner);
+
+    client.approve_until(&owner, &operator, &1, &100);
+    env.ledger().set_timestamp(200);
+    assert!(!client.is_approved(&operator, &1));
+
+    // A permit grants a lasting approval, dropping the stale expiry
+    client.permit(&owner, &operator, &1, &300);
+    assert!(client.is_approved(&operator, &1));
+    assert_eq!(client.approvals_expiring_before(&1, &u64::MAX).len(), 0);
+}
+
+#[test]
+#[should_panic(expected = "Permit expired")]
+fn test_permit_expired() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let owner = Address::generate(&env);
+    let operator = Address::generate(&env);
+    client.add_to_whitelist(&admin, &owner);
+    client.mint(&owner);
+
+    env.ledger().set_timestamp(101);
+    client.permit(&owner, &operator, &1, &100);
+}
+
+#[test]
+fn test_permit_replay() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let owner = Address::generate(&env);
+    let operator = Address::generate(&env);
+    client.add_to_whitelist(&admin, &owner);
+    client.mint(&owner);
+
+    mock_permit(&env, &client, &owner, &operator, 100, 0);
+    client.permit(&owner, &operator, &1, &100);
+
+    // The nonce has moved on, so an authorization for the old nonce no longer matches
+    mock_permit(&env, &client, &owner, &operator, 100, 0);
+    assert!(client.try_permit(&owner, &operator, &1, &100).is_err());
+    assert_eq!(client.permit_nonce(&owner), 1);
+}
+
+#[test]
+fn test_next_is_reserved() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    client.set_reserved_range(&admin, &1, &2);
+    assert!(client.next_is_reserved());
+
+    // Public minting jumps past the reserved block
+    let alice = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+    assert_eq!(client.owner_of(&3), alice);
+    assert!(!client.next_is_reserved());
+
+    // A later reserved block ahead of the cursor is flagged once it is next
+    client.set_reserved_range(&admin, &4, &5);
+    assert!(client.next_is_reserved());
+}
+
+#[test]
+fn test_mint_onchain() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    let attributes = vec![
+        &env,
+        (String::from_str(&env, "Coat"), String::from_str(&env, "Bay")),
+        (String::from_str(&env, "Speed"), String::from_str(&env, "9")),
+    ];
+    client.mint_onchain(&alice, &String::from_str(&env, "Stallion #1"), &attributes);
+
+    assert_eq!(client.owner_of(&1), alice);
+    assert_eq!(
+        client.token_json(&1),
+        String::from_str(
+            &env,
+            r#"{"name":"Stallion #1","attributes":[{"trait_type":"Coat","value":"Bay"},{"trait_type":"Speed","value":"9"}]}"#
+        )
+    );
+
+    // There are no URLs to read back for an on-chain token
+    let onchain = soroban_sdk::Error::from_contract_error(Error::OnchainMetadata as u32);
+    assert_eq!(client.try_get_token_uris(&1), Err(Ok(onchain)));
+    assert_eq!(client.try_get_token_image(&1), Err(Ok(onchain)));
+    assert!(client.get_nft_by_address(&alice).is_none());
+}
+
+#[test]
+#[should_panic(expected = "Invalid character in on-chain metadata")]
+fn test_mint_onchain_rejects_quotes() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint_onchain(&alice, &String::from_str(&env, "\"Stallion\""), &vec![&env]);
+}
+
+#[test]
+#[should_panic(expected = "On-chain metadata is not valid UTF-8")]
+fn test_mint_onchain_rejects_invalid_utf8() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    let attributes = vec![&env, (String::from_str(&env, "Coat"), String::from_bytes(&env, &[0x42, 0xff, 0x79]))];
+    client.mint_onchain(&alice, &String::from_str(&env, "Stallion #1"), &attributes);
+}
+
+#[test]
+fn test_approvals_expiring_before() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let short = Address::generate(&env);
+    let long = Address::generate(&env);
+    let permanent = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+
+    client.approve_until(&alice, &short, &1, &100);
+    client.approve_until(&alice, &long, &1, &500);
+    client.approve(&alice, &permanent, &1);
+
+    assert_eq!(client.approvals_expiring_before(&1, &200), vec![&env, short.clone()]);
+    assert_eq!(client.approvals_expiring_before(&1, &1000).len(), 2);
+
+    env.ledger().set_timestamp(101);
+    assert!(!client.is_approved(&short, &1));
+    assert!(client.is_approved(&long, &1));
+    assert!(client.is_approved(&permanent, &1));
+}
+
+#[test]
+fn test_transfer_with_max_approvals() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let carol = Address::generate(&env);
+    let bob = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.add_to_whitelist(&admin, &carol);
+    client.mint(&carol);
+    client.mint(&alice);
+
+    // Baseline cost of a transfer with no approvals
+    env.budget().reset_default();
+    client.transfer(&carol, &bob, &1);
+    let empty_cost = env.budget().cpu_instruction_cost();
+
+    let mut operators = std::vec::Vec::new();
+    for _ in 0..StallionNFT::MAX_APPROVALS {
+        let operator = Address::generate(&env);
+        client.approve(&alice, &operator, &2);
+        operators.push(operator);
+    }
+    assert!(client.try_approve(&alice, &Address::generate(&env), &2).is_err());
+
+    // Clearing a full approval list on transfer is a single removal
+    env.budget().reset_default();
+    client.transfer(&alice, &bob, &2);
+    let full_cost = env.budget().cpu_instruction_cost();
+    assert_eq!(client.owner_of(&2), bob);
+    assert!(full_cost <= empty_cost + empty_cost / 4);
+    for operator in operators.iter() {
+        assert!(!client.is_approved(operator, &2));
+    }
+}
+
+#[test]
+fn test_expired_approvals_free_cap() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+
+    let expiring = Address::generate(&env);
+    client.approve_until(&alice, &expiring, &1, &100);
+    for _ in 1..StallionNFT::MAX_APPROVALS {
+        client.approve(&alice, &Address::generate(&env), &1);
+    }
+    assert!(client.try_approve(&alice, &Address::generate(&env), &1).is_err());
+
+    // Once the time-limited approval lapses its slot can be reused
+    env.ledger().set_timestamp(101);
+    let operator = Address::generate(&env);
+    client.approve(&alice, &operator, &1);
+    assert!(client.is_approved(&operator, &1));
+    assert!(!client.is_approved(&expiring, &1));
+    assert!(client.try_approve(&alice, &Address::generate(&env), &1).is_err());
+}
 
 #[test]
-fn test() {
+fn test_effective_cap() {
     let env = Env::default();
-    let contract_id = env.register(Contract, ());
-    let client = ContractClient::new(&env, &contract_id);
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let regular = Address::generate(&env);
+    let collector = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    for address in [&regular, &collector, &treasury] {
+        client.add_to_whitelist(&admin, address);
+    }
+
+    // One mint per address by default
+    assert_eq!(client.effective_cap(&regular), 1);
+    client.mint(&regular);
+    assert!(client.try_mint(&regular).is_err());
+
+    client.set_mint_cap(&admin, &2);
+    assert_eq!(client.effective_cap(&regular), 2);
+    client.mint(&regular);
+    assert_eq!(client.mint_count(&regular), 2);
+
+    client.set_mint_cap_override(&admin, &collector, &Some(3));
+    assert_eq!(client.effective_cap(&collector), 3);
+    client.set_mint_cap_override(&admin, &collector, &None);
+    assert_eq!(client.effective_cap(&collector), 2);
+
+    client.set_exempt(&admin, &treasury, &true);
+    assert_eq!(client.effective_cap(&treasury), u32::MAX);
+}
+
+#[test]
+fn test_never_transferred() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.add_to_whitelist(&admin, &bob);
+    client.mint(&alice);
+    client.mint(&bob);
+
+    client.transfer(&alice, &bob, &1);
+    assert_eq!(client.transfer_count(&1), 1);
+    assert_eq!(client.transfer_count(&2), 0);
+    assert_eq!(client.never_transferred(&1, &10), vec![&env, 2]);
+    assert_eq!(client.never_transferred(&1, &1), vec![&env]);
+}
+
+#[test]
+fn test_ownership_history() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let carol = Address::generate(&env);
+    let dave = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    env.ledger().set_timestamp(50);
+    client.mint(&alice);
+    assert_eq!(client.ownership_history(&1), vec![&env, (alice.clone(), 50u64)]);
+
+    env.ledger().set_timestamp(100);
+    client.transfer(&alice, &bob, &1);
+    env.ledger().set_timestamp(200);
+    client.transfer(&bob, &carol, &1);
+    env.ledger().set_timestamp(300);
+    client.transfer(&carol, &dave, &1);
 
-    let words = client.hello(&String::from_str(&env, "Dev"));
     assert_eq!(
-        words,
+        client.ownership_history(&1),
         vec![
             &env,
-            String::from_str(&env, "Hello"),
-            String::from_str(&env, "Dev"),
+            (alice.clone(), 50u64),
+            (bob.clone(), 100u64),
+            (carol.clone(), 200u64),
+            (dave.clone(), 300u64)
         ]
     );
+
+    // Only the most recent entries are kept
+    for _ in 0..StallionNFT::MAX_HISTORY {
+        client.transfer(&dave, &alice, &1);
+        client.transfer(&alice, &dave, &1);
+    }
+    let history = client.ownership_history(&1);
+    assert_eq!(history.len(), StallionNFT::MAX_HISTORY);
+    assert_eq!(history.last().unwrap(), (dave, 300u64));
+}
+
+#[test]
+fn test_phase_cap() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    client.set_current_phase(&admin, &1);
+    client.set_phase_cap(&admin, &1, &2);
+
+    let minters = [Address::generate(&env), Address::generate(&env), Address::generate(&env)];
+    for minter in minters.iter() {
+        client.add_to_whitelist(&admin, minter);
+    }
+    client.mint(&minters[0]);
+    client.mint(&minters[1]);
+    assert_eq!(client.phase_minted(&1), 2);
+
+    // The presale cap is exhausted even though overall supply remains
+    assert!(client.try_mint(&minters[2]).is_err());
+
+    client.set_current_phase(&admin, &2);
+    client.mint(&minters[2]);
+    assert_eq!(client.phase_minted(&2), 1);
+    assert_eq!(client.phase_cap(&2), None);
+}
+
+#[test]
+fn test_exempt_exceeds_holding_cap() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let treasury = Address::generate(&env);
+    let alice = Address::generate(&env);
+    client.add_to_whitelist(&admin, &treasury);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&treasury);
+    client.mint(&alice);
+    client.set_holding_cap(&admin, &1);
+
+    assert!(client.try_transfer(&alice, &treasury, &2).is_err());
+
+    client.set_exempt(&admin, &treasury, &true);
+    assert!(client.is_exempt(&treasury));
+    client.transfer(&alice, &treasury, &2);
+    assert_eq!(client.owner_of(&1), treasury);
+    assert_eq!(client.owner_of(&2), treasury);
+}
+
+fn create_token<'a>(env: &Env) -> (Address, token::StellarAssetClient<'a>) {
+    let issuer = Address::generate(env);
+    let token_id = env.register_stellar_asset_contract_v2(issuer).address();
+    (token_id.clone(), token::StellarAssetClient::new(env, &token_id))
+}
+
+#[test]
+fn test_configure() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let (payment_token, token_admin) = create_token(&env);
+    let treasury = Address::generate(&env);
+    let artist = Address::generate(&env);
+    let config = SaleConfig {
+        payment_token: payment_token.clone(),
+        treasury: treasury.clone(),
+        price: 50,
+        sale_start: 10,
+        sale_end: 1000,
+        sale_active: true,
+        holding_cap: Some(3),
+        royalty: Royalty { receiver: artist.clone(), bps: 500 },
+    };
+    client.configure(&admin, &config);
+
+    assert_eq!(client.sale_config(), Some(config));
+    assert_eq!(client.holding_cap(), Some(3));
+    assert_eq!(client.effective_royalty(&1), (artist, 500));
+
+    let alice = Address::generate(&env);
+    token_admin.mint(&alice, &100);
+    client.add_to_whitelist(&admin, &alice);
+
+    // The window has not opened yet
+    assert!(client.try_mint(&alice).is_err());
+
+    env.ledger().set_timestamp(10);
+    client.mint(&alice);
+    let payments = token::Client::new(&env, &payment_token);
+    assert_eq!(payments.balance(&alice), 50);
+    assert_eq!(payments.balance(&treasury), 50);
+
+    // A zero cap would block every mint; None lifts the cap instead
+    let mut uncapped = client.sale_config().unwrap();
+    uncapped.holding_cap = Some(0);
+    assert!(client.try_configure(&admin, &uncapped).is_err());
+    uncapped.holding_cap = None;
+    client.configure(&admin, &uncapped);
+    assert_eq!(client.holding_cap(), None);
+}
+
+#[test]
+#[should_panic(expected = "Royalty exceeds 100%")]
+fn test_configure_rejects_invalid_royalty() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let (payment_token, _) = create_token(&env);
+    let receiver = Address::generate(&env);
+    client.configure(
+        &admin,
+        &SaleConfig {
+            payment_token,
+            treasury: receiver.clone(),
+            price: 0,
+            sale_start: 0,
+            sale_end: 1000,
+            sale_active: true,
+            holding_cap: Some(1),
+            royalty: Royalty { receiver, bps: 10_001 },
+        },
+    );
+}
+
+#[test]
+fn test_last_mint_receipt() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    env.ledger().set_timestamp(50);
+    client.mint(&alice);
+
+    assert_eq!(
+        client.last_mint_receipt(&alice),
+        Some(MintReceipt {
+            token_id: 1,
+            timestamp: 50,
+            phase: client.current_phase(),
+            payment_token: None,
+            price: 0,
+        })
+    );
+    assert_eq!(client.last_mint_receipt(&Address::generate(&env)), None);
+}
+
+#[test]
+fn test_get_token_uris() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+
+    assert_eq!(client.get_token_uris(&1), (client.get_token_image(&1), client.get_token_metadata(&1)));
+}
+
+#[test]
+fn test_contract_uri() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    assert_eq!(client.contract_uri(), String::from_str(&env, ""));
+
+    let uri = String::from_str(&env, "ipfs://bafybeibc5sgo2plmjkq2tzmhrn54bk3crhnc23zd2msg4ea7a4pxrkgfna/collection.json");
+    client.set_contract_uri(&admin, &uri);
+    assert_eq!(client.contract_uri(), uri);
+
+    assert!(client.try_set_contract_uri(&Address::generate(&env), &uri).is_err());
+}
+
+#[test]
+fn test_has_extension() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    assert!(!client.has_extension(&symbol_short!("royalty")));
+    client.set_royalty(&admin, &Address::generate(&env), &500, &false);
+    assert!(client.has_extension(&symbol_short!("royalty")));
+
+    assert!(!client.has_extension(&symbol_short!("merkle")));
+    assert!(client.has_extension(&symbol_short!("soulbound")));
+    assert!(client.has_extension(&Symbol::new(&env, "enumerable")));
+    assert!(!client.has_extension(&symbol_short!("enumerabl")));
+    assert!(!client.has_extension(&symbol_short!("unknown")));
+}
+
+#[test]
+fn test_admin_handover() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let successor = Address::generate(&env);
+    assert_eq!(client.pending_admin(), None);
+    client.propose_admin(&admin, &successor);
+    assert_eq!(client.pending_admin(), Some(successor.clone()));
+
+    // Nothing changes until the successor accepts
+    assert!(client.try_accept_admin(&Address::generate(&env)).is_err());
+    assert!(client.try_set_contract_uri(&successor, &String::from_str(&env, "ipfs://new")).is_err());
+
+    client.accept_admin(&successor);
+    assert_eq!(client.pending_admin(), None);
+    client.set_contract_uri(&successor, &String::from_str(&env, "ipfs://new"));
+    assert!(client.try_set_contract_uri(&admin, &String::from_str(&env, "ipfs://old")).is_err());
+}
+
+#[test]
+fn test_reveal_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.add_to_whitelist(&admin, &bob);
+    client.mint(&alice);
+    client.mint(&bob);
+    assert_eq!(client.unrevealed_tokens(&1, &10), vec![&env, 1, 2]);
+
+    client.set_token_metadata(&admin, &1, &String::from_str(&env, "ipfs://revealed/1.json"));
+    client.reveal_token(&admin, &1);
+    let events = env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![&env, (client.address.clone(), (symbol_short!("Reveal"),).into_val(&env), 1i128.into_val(&env))]
+    );
+
+    assert!(client.is_revealed(&1));
+    assert_eq!(client.unrevealed_tokens(&1, &10), vec![&env, 2]);
+    assert!(client.try_reveal_token(&admin, &3).is_err());
+}
+
+#[test]
+fn test_transfer_missing_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+    let not_found = soroban_sdk::Error::from_contract_error(Error::TokenNotFound as u32);
+
+    // Never minted
+    assert_eq!(client.try_transfer(&alice, &bob, &7), Err(Ok(not_found)));
+    assert_eq!(client.try_transfer_from(&bob, &alice, &bob, &7), Err(Ok(not_found)));
+    assert_eq!(client.try_approve(&alice, &bob, &7), Err(Ok(not_found)));
+    assert_eq!(client.try_approve_until(&alice, &bob, &7, &100), Err(Ok(not_found)));
+    assert_eq!(client.try_permit(&alice, &bob, &7, &100), Err(Ok(not_found)));
+
+    // Burned
+    client.burn(&alice, &1);
+    assert_eq!(client.try_transfer(&alice, &bob, &1), Err(Ok(not_found)));
+    assert_eq!(client.try_transfer_from(&bob, &alice, &bob, &1), Err(Ok(not_found)));
+    assert_eq!(client.try_approve(&alice, &bob, &1), Err(Ok(not_found)));
+    assert_eq!(client.try_approve_until(&alice, &bob, &1, &100), Err(Ok(not_found)));
+    assert_eq!(client.try_permit(&alice, &bob, &1, &100), Err(Ok(not_found)));
+}
+
+#[test]
+fn test_burned_by() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.add_to_whitelist(&admin, &bob);
+    client.mint(&alice);
+    client.mint(&bob);
+    client.transfer(&bob, &alice, &2);
+
+    client.burn(&alice, &1);
+    client.burn(&alice, &2);
+    assert_eq!(client.burned_by(&alice), 2);
+    assert_eq!(client.burned_by(&bob), 0);
+}
+
+#[test]
+fn test_royalties_enforced() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let artist = Address::generate(&env);
+    assert!(!client.royalties_enforced());
+
+    client.set_royalty(&admin, &artist, &500, &true);
+    assert!(client.royalties_enforced());
+
+    client.set_royalty(&admin, &artist, &500, &false);
+    assert!(!client.royalties_enforced());
+}
+
+#[test]
+fn test_add_to_whitelist_idempotent() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    assert!(client.add_to_whitelist(&admin, &alice));
+    assert!(!client.add_to_whitelist(&admin, &alice));
+    assert_eq!(client.get_whitelist(), vec![&env, alice]);
+}
+
+#[test]
+fn test_last_mint_at() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    assert_eq!(client.last_mint_at(), None);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.add_to_whitelist(&admin, &bob);
+
+    env.ledger().set_timestamp(100);
+    client.mint(&alice);
+    assert_eq!(client.last_mint_at(), Some(100));
+
+    env.ledger().set_timestamp(250);
+    client.mint(&bob);
+    assert_eq!(client.last_mint_at(), Some(250));
+}
+
+#[test]
+fn test_scatter() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let treasury = Address::generate(&env);
+    let minters = [Address::generate(&env), Address::generate(&env), Address::generate(&env)];
+    for minter in minters.iter() {
+        client.add_to_whitelist(&admin, minter);
+        client.mint(minter);
+    }
+    for (i, minter) in minters.iter().enumerate() {
+        client.transfer(minter, &treasury, &(i as i128 + 1));
+    }
+
+    let recipients = [Address::generate(&env), Address::generate(&env), Address::generate(&env)];
+    client.scatter(
+        &treasury,
+        &vec![
+            &env,
+            (recipients[0].clone(), 1),
+            (recipients[1].clone(), 2),
+            (recipients[2].clone(), 3),
+        ],
+    );
+
+    assert_eq!(client.owner_of(&1), recipients[0]);
+    assert_eq!(client.owner_of(&2), recipients[1]);
+    assert_eq!(client.owner_of(&3), recipients[2]);
+    assert!(!client.all_holders(&0, &10).contains(&treasury));
+}
+
+#[test]
+#[should_panic(expected = "Not the token owner")]
+fn test_scatter_requires_ownership_of_every_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.add_to_whitelist(&admin, &bob);
+    client.mint(&alice);
+    client.mint(&bob);
+
+    client.scatter(&alice, &vec![&env, (bob.clone(), 1), (alice.clone(), 2)]);
+}
+
+#[test]
+fn test_is_metadata_frozen() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+    assert_eq!(client.is_metadata_frozen(&1), (false, false));
+
+    client.freeze_image(&admin, &1);
+    assert_eq!(client.is_metadata_frozen(&1), (true, false));
+    assert!(client.try_set_token_image(&admin, &1, &String::from_str(&env, "ipfs://new-image")).is_err());
+
+    // Metadata stays editable until it is frozen separately
+    let metadata = String::from_str(&env, "ipfs://new-metadata");
+    client.set_token_metadata(&admin, &1, &metadata);
+    assert_eq!(client.get_token_metadata(&1), metadata);
+
+    client.freeze_metadata(&admin, &1);
+    assert_eq!(client.is_metadata_frozen(&1), (true, true));
+}
+
+#[test]
+fn test_whitelist_status() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let waiting = Address::generate(&env);
+    let minted = Address::generate(&env);
+    let outsider = Address::generate(&env);
+    client.add_to_whitelist(&admin, &waiting);
+    client.add_to_whitelist(&admin, &minted);
+    client.mint(&minted);
+
+    assert_eq!(client.whitelist_status(&waiting), (true, false));
+    assert_eq!(client.whitelist_status(&minted), (true, true));
+    assert_eq!(client.whitelist_status(&outsider), (false, false));
+}
+
+#[test]
+fn test_mint_quote() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let (payment_token, _) = create_token(&env);
+    let treasury = Address::generate(&env);
+    client.configure(
+        &admin,
+        &SaleConfig {
+            payment_token: payment_token.clone(),
+            treasury: treasury.clone(),
+            price: 50,
+            sale_start: 0,
+            sale_end: 1000,
+            sale_active: true,
+            holding_cap: Some(1),
+            royalty: Royalty { receiver: treasury, bps: 0 },
+        },
+    );
+    client.set_free_mint_window(&admin, &100, &200);
+
+    let alice = Address::generate(&env);
+    let outsider = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+
+    // Paid phase
+    assert_eq!(client.mint_quote(&alice), (payment_token.clone(), 50));
+
+    // Free-mint window applies to whitelisted addresses only
+    env.ledger().set_timestamp(150);
+    assert_eq!(client.mint_quote(&alice), (payment_token.clone(), 0));
+    assert_eq!(client.mint_quote(&outsider), (payment_token.clone(), 50));
+
+    // Minting inside the window charges nothing, so no balance is needed
+    client.mint(&alice);
+    assert_eq!(client.owner_of(&1), alice);
+}
+
+#[test]
+fn test_set_approval_for_all_event() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _admin) = setup(&env);
+
+    let owner = Address::generate(&env);
+    let operator = Address::generate(&env);
+
+    for approved in [true, false] {
+        client.set_approval_for_all(&owner, &operator, &approved);
+        assert_eq!(client.is_approved_for_all(&owner, &operator), approved);
+
+        let events = env.events().all();
+        assert_eq!(
+            events.slice(events.len() - 1..),
+            vec![
+                &env,
+                (
+                    client.address.clone(),
+                    (Symbol::new(&env, "ApprovalForAll"),).into_val(&env),
+                    (owner.clone(), operator.clone(), approved).into_val(&env),
+                ),
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_release_reserved_from_treasury() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    client.set_reserved_range(&admin, &1, &3);
+    client.mint_reserved(&admin, &client.address, &1);
+    client.mint_reserved(&admin, &client.address, &2);
+    client.mint_reserved(&admin, &admin, &3);
+    assert_eq!(client.reserved_in_treasury(), vec![&env, 1, 2]);
+
+    let winner = Address::generate(&env);
+    client.release_reserved(&admin, &1, &winner);
+    assert_eq!(client.owner_of(&1), winner);
+    assert_eq!(client.reserved_in_treasury(), vec![&env, 2]);
+
+    // Reserved tokens held elsewhere are not the treasury's to release
+    assert!(client.try_release_reserved(&admin, &3, &winner).is_err());
+}
+
+#[test]
+fn test_reserved_reconciliation() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    assert_eq!(client.reserved_reconciliation(), (0, 0));
+
+    client.set_reserved_range(&admin, &1, &3);
+    for token_id in 1..=3 {
+        client.mint_reserved(&admin, &admin, &token_id);
+    }
+    assert_eq!(client.reserved_reconciliation(), (3, 3));
+
+    client.transfer(&admin, &Address::generate(&env), &2);
+    assert_eq!(client.reserved_reconciliation(), (3, 2));
+}
+
+#[test]
+fn test_assign_tiers() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let gold = Address::generate(&env);
+    let silver = Address::generate(&env);
+    client.assign_tiers(&admin, &vec![&env, (gold.clone(), 1u32), (silver.clone(), 2u32)]);
+
+    assert_eq!(client.tier_of(&gold), Some(1));
+    assert_eq!(client.tier_of(&silver), Some(2));
+    assert_eq!(client.tier_of(&Address::generate(&env)), None);
+
+    let mut entries = vec![&env];
+    for _ in 0..=StallionNFT::MAX_BATCH {
+        entries.push_back((Address::generate(&env), 3u32));
+    }
+    assert!(client.try_assign_tiers(&admin, &entries).is_err());
+}
+
+#[test]
+fn test_is_mintable_id() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+    client.set_reserved_range(&admin, &10, &20);
+
+    assert!(!client.is_mintable_id(&1));
+    assert!(!client.is_mintable_id(&15));
+    assert!(!client.is_mintable_id(&0));
+    assert!(!client.is_mintable_id(&2001));
+    assert!(client.is_mintable_id(&2));
+
+    // IDs already passed by public minting are never mintable again, even once the range moves
+    client.set_reserved_range(&admin, &2, &3);
+    let bob = Address::generate(&env);
+    client.add_to_whitelist(&admin, &bob);
+    client.mint(&bob);
+    assert_eq!(client.owner_of(&4), bob);
+    client.set_reserved_range(&admin, &10, &20);
+    assert!(!client.is_mintable_id(&2));
+    assert!(client.is_mintable_id(&5));
+
+    // A burned reserved ID stays retired after the range moves past it
+    client.mint_reserved(&admin, &admin, &10);
+    client.burn(&admin, &10);
+    client.set_reserved_range(&admin, &11, &20);
+    assert!(!client.is_mintable_id(&10));
+}
+
+#[test]
+fn test_burned_reserved_id_not_reminted() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    client.set_reserved_range(&admin, &1, &3);
+    client.mint_reserved(&admin, &admin, &1);
+    client.burn(&admin, &1);
+    assert!(client.try_mint_reserved(&admin, &admin, &1).is_err());
+    client.mint_reserved(&admin, &admin, &2);
+    assert_eq!(client.owner_of(&2), admin);
+}
+
+#[test]
+fn test_get_nft_by_address_finds_reserved() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let treasury = Address::generate(&env);
+    client.set_reserved_range(&admin, &1990, &2000);
+    client.mint_reserved(&admin, &treasury, &1995);
+
+    let found = client.get_nft_by_address(&treasury).unwrap();
+    assert_eq!(found.token_id, 1995);
+    assert_eq!(found.address, treasury);
+}
+
+#[test]
+fn test_reserved_mint_starts_history() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let treasury = Address::generate(&env);
+    client.set_reserved_range(&admin, &1, &3);
+    env.ledger().set_timestamp(10);
+    client.mint_reserved(&admin, &treasury, &2);
+    assert_eq!(client.ownership_history(&2), vec![&env, (treasury, 10u64)]);
+}
+
+#[test]
+fn test_lock_reserve() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    client.set_reserved_range(&admin, &1, &3);
+    client.mint_reserved(&admin, &admin, &1);
+    assert!(!client.is_reserve_locked());
+
+    client.lock_reserve(&admin);
+    assert!(client.is_reserve_locked());
+    assert!(client.try_mint_reserved(&admin, &admin, &2).is_err());
+
+    // Public minting carries on past the reserved range
+    let alice = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+    assert_eq!(client.owner_of(&4), alice);
+}
+
+#[test]
+fn test_whitelist_claimed_count() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let listed = [Address::generate(&env), Address::generate(&env), Address::generate(&env)];
+    for address in listed.iter() {
+        client.add_to_whitelist(&admin, address);
+    }
+    assert_eq!(client.whitelist_claimed_count(), 0);
+
+    client.mint(&listed[0]);
+    client.mint(&listed[2]);
+    assert_eq!(client.whitelist_claimed_count(), 2);
+}
+
+#[test]
+fn test_next_available_public_id() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    assert_eq!(client.next_available_public_id(), Some(1));
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.add_to_whitelist(&admin, &bob);
+    client.set_reserved_range(&admin, &2, &3);
+    client.mint(&alice);
+    assert_eq!(client.next_available_public_id(), Some(4));
+
+    // Burn reserved ID 2, then shrink the range so the scan from 2 has to step over the burned ID
+    client.mint_reserved(&admin, &admin, &2);
+    client.burn(&admin, &2);
+    client.set_reserved_range(&admin, &3, &3);
+
+    // The burned ID is not handed out again and the reserved range is skipped
+    assert_eq!(client.next_available_public_id(), Some(4));
+    client.mint(&bob);
+    assert_eq!(client.owner_of(&4), bob);
+    assert_eq!(client.next_available_public_id(), Some(5));
+}
+
+#[test]
+fn test_remove_from_whitelist_positions() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let addresses = [
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+    ];
+    for address in addresses.iter() {
+        client.add_to_whitelist(&admin, address);
+    }
+
+    // First
+    client.remove_from_whitelist(&admin, &addresses[0]);
+    assert_eq!(
+        client.get_whitelist(),
+        vec![&env, addresses[1].clone(), addresses[2].clone(), addresses[3].clone(), addresses[4].clone()]
+    );
+
+    // Middle
+    client.remove_from_whitelist(&admin, &addresses[2]);
+    assert_eq!(client.get_whitelist(), vec![&env, addresses[1].clone(), addresses[3].clone(), addresses[4].clone()]);
+
+    // Last
+    client.remove_from_whitelist(&admin, &addresses[4]);
+    assert_eq!(client.get_whitelist(), vec![&env, addresses[1].clone(), addresses[3].clone()]);
+}
+
+#[test]
+#[should_panic(expected = "Address not whitelisted")]
+fn test_remove_from_whitelist_missing() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    client.add_to_whitelist(&admin, &Address::generate(&env));
+    client.remove_from_whitelist(&admin, &Address::generate(&env));
+}
+
+#[test]
+fn test_effective_operator_approval() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let owner = Address::generate(&env);
+    let marketplace = Address::generate(&env);
+    let shady = Address::generate(&env);
+    client.set_approval_for_all(&owner, &marketplace, &true);
+    client.set_approval_for_all(&owner, &shady, &true);
+    client.set_blacklisted(&admin, &shady, &true);
+
+    assert!(client.effective_operator_approval(&owner, &marketplace));
+    assert!(!client.effective_operator_approval(&owner, &shady));
+    assert!(client.is_approved_for_all(&owner, &shady));
+}
+
+#[test]
+#[cfg(feature = "testutils")]
+fn test_seed_mint_then_transfer() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let metadata = String::from_str(&env, "ipfs://seed-metadata");
+    let image = String::from_str(&env, "ipfs://seed-image");
+    client.test_seed_mint(&alice, &42, &metadata, &image);
+    assert_eq!(client.owner_of(&42), alice);
+    assert_eq!(client.get_token_uris(&42), (image.clone(), metadata.clone()));
+
+    client.transfer(&alice, &bob, &42);
+    assert_eq!(client.owner_of(&42), bob);
+
+    // A burned ID cannot be seeded again
+    client.burn(&bob, &42);
+    assert!(client.try_test_seed_mint(&alice, &42, &metadata, &image).is_err());
+}
+
+#[test]
+fn test_phase_info() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let presale = PhaseInfo { cap: Some(500), start: 0, end: 100, whitelist_gated: true };
+    let public = PhaseInfo { cap: None, start: 100, end: 1000, whitelist_gated: false };
+    client.configure_phase(&admin, &1, &presale);
+    client.configure_phase(&admin, &2, &public);
+    client.configure_phase(&admin, &2, &public);
+
+    assert_eq!(client.phase_count(), 2);
+    assert_eq!(client.phase_info(&1), presale);
+    assert_eq!(client.phase_info(&2), public);
+
+    // The public phase lets anyone mint once it opens
+    client.set_current_phase(&admin, &2);
+    let stranger = Address::generate(&env);
+    assert!(client.try_mint(&stranger).is_err());
+    env.ledger().set_timestamp(100);
+    client.mint(&stranger);
+    assert_eq!(client.owner_of(&1), stranger);
+}
+
+#[test]
+fn test_my_approved_tokens() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let owner = Address::generate(&env);
+    let marketplace = Address::generate(&env);
+    let minters = [Address::generate(&env), Address::generate(&env), Address::generate(&env)];
+    for (i, minter) in minters.iter().enumerate() {
+        client.add_to_whitelist(&admin, minter);
+        client.mint(minter);
+        client.transfer(minter, &owner, &(i as i128 + 1));
+    }
+
+    client.approve(&owner, &marketplace, &1);
+    client.approve(&owner, &marketplace, &3);
+    assert_eq!(client.my_approved_tokens(&marketplace, &owner), vec![&env, 1, 3]);
+
+    client.set_approval_for_all(&owner, &marketplace, &true);
+    assert_eq!(client.my_approved_tokens(&marketplace, &owner), vec![&env, 1, 2, 3]);
+
+    client.set_blacklisted(&admin, &marketplace, &true);
+    assert_eq!(client.my_approved_tokens(&marketplace, &owner), vec![&env]);
+}
+
+#[test]
+fn test_auto_pause_on_mint_velocity() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    client.set_mint_rate_limit(&admin, &2, &60);
+    let minters = [
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+    ];
+    for minter in minters.iter() {
+        client.add_to_whitelist(&admin, minter);
+    }
+
+    // The first mint falls out of the window before the next ones land
+    client.mint(&minters[0]);
+    env.ledger().set_timestamp(60);
+    client.mint(&minters[1]);
+    client.mint(&minters[2]);
+    assert!(!client.is_paused());
+
+    // A third mint inside the window exceeds the threshold and trips the breaker
+    client.mint(&minters[3]);
+    assert!(client.is_paused());
+    let events = env.events().all();
+    assert_eq!(
+        events.slice(events.len() - 1..),
+        vec![
+            &env,
+            (client.address.clone(), (symbol_short!("AutoPause"),).into_val(&env), (3u32, 60u64).into_val(&env)),
+        ]
+    );
+    assert!(client.try_mint(&minters[4]).is_err());
+
+    // Only the admin can resume
+    client.unpause(&admin);
+    client.mint(&minters[4]);
+}
+
+#[test]
+fn test_mint_rate_limit_bounds() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    assert!(client.try_set_mint_rate_limit(&admin, &(StallionNFT::MAX_RATE_LIMIT_MINTS + 1), &60).is_err());
+
+    // A window reaching the end of time keeps every mint in it without overflowing
+    client.set_mint_rate_limit(&admin, &2, &u64::MAX);
+    let minters = [Address::generate(&env), Address::generate(&env), Address::generate(&env)];
+    for minter in minters.iter() {
+        client.add_to_whitelist(&admin, minter);
+    }
+    env.ledger().set_timestamp(1_000);
+    client.mint(&minters[0]);
+    env.ledger().set_timestamp(2_000);
+    client.mint(&minters[1]);
+    assert!(!client.is_paused());
+    client.mint(&minters[2]);
+    assert!(client.is_paused());
+}
+
+#[test]
+fn test_tokens_of_owner_ascending() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let collector = Address::generate(&env);
+    let minters = [
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+    ];
+    for minter in minters.iter() {
+        client.add_to_whitelist(&admin, minter);
+        client.mint(minter);
+    }
+
+    // Arrive out of order
+    for token_id in [4, 1, 5, 2] {
+        client.transfer(&minters[token_id as usize - 1], &collector, &token_id);
+    }
+    assert_eq!(client.tokens_of_owner(&collector), vec![&env, 1, 2, 4, 5]);
+
+    // Removals keep the remaining order, and re-inserts land in place
+    client.transfer(&collector, &minters[1], &2);
+    assert_eq!(client.tokens_of_owner(&collector), vec![&env, 1, 4, 5]);
+    client.transfer(&minters[2], &collector, &3);
+    client.transfer(&minters[1], &collector, &2);
+
+    let owned = client.tokens_of_owner(&collector);
+    assert_eq!(owned, vec![&env, 1, 2, 3, 4, 5]);
+    for i in 1..owned.len() {
+        assert!(owned.get_unchecked(i - 1) < owned.get_unchecked(i));
+    }
+}
+
+fn setup_refunds<'a>(env: &Env, client: &StallionNFTClient, admin: &Address) -> token::Client<'a> {
+    let (payment_token, token_admin) = create_token(env);
+    let treasury = Address::generate(env);
+    client.configure(
+        admin,
+        &SaleConfig {
+            payment_token: payment_token.clone(),
+            treasury: treasury.clone(),
+            price: 100,
+            sale_start: 0,
+            sale_end: 10_000,
+            sale_active: true,
+            holding_cap: Some(5),
+            royalty: Royalty { receiver: treasury.clone(), bps: 0 },
+        },
+    );
+    client.set_refund_policy(admin, &3600, &60);
+
+    let payments = token::Client::new(env, &payment_token);
+    token_admin.mint(&treasury, &1000);
+    payments.approve(&treasury, &client.address, &1000, &(env.ledger().sequence() + 1000));
+    payments
+}
+
+#[test]
+fn test_burn_for_refund_in_window() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+    let payments = setup_refunds(&env, &client, &admin);
+
+    let alice = Address::generate(&env);
+    StellarAssetClient::new(&env, &payments.address).mint(&alice, &100);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+    assert_eq!(payments.balance(&alice), 0);
+
+    env.ledger().set_timestamp(3600);
+    client.burn_for_refund(&alice, &1);
+    assert_eq!(payments.balance(&alice), 60);
+    assert!(client.tokens_of_owner(&alice).is_empty());
+}
+
+#[test]
+fn test_burn_for_refund_capped_at_price_paid() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+    let payments = setup_refunds(&env, &client, &admin);
+
+    // A refund window reaching the end of time does not overflow
+    client.set_refund_policy(&admin, &u64::MAX, &60);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    StellarAssetClient::new(&env, &payments.address).mint(&alice, &100);
+    client.add_to_whitelist(&admin, &alice);
+    client.add_to_whitelist(&admin, &bob);
+    client.mint(&alice);
+    env.ledger().set_timestamp(100);
+    client.burn_for_refund(&alice, &1);
+    assert_eq!(payments.balance(&alice), 60);
+
+    // Tokens minted for free in the free-mint window pay nothing back
+    client.set_free_mint_window(&admin, &200, &300);
+    env.ledger().set_timestamp(250);
+    client.mint(&bob);
+    assert!(client.try_burn_for_refund(&bob, &2).is_err());
+    assert_eq!(client.owner_of(&2), bob);
+    assert_eq!(payments.balance(&bob), 0);
+}
+
+#[test]
+fn test_burn_for_refund_in_token_paid() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+    let payments = setup_refunds(&env, &client, &admin);
+
+    let alice = Address::generate(&env);
+    StellarAssetClient::new(&env, &payments.address).mint(&alice, &100);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+
+    // Switching the payment token afterwards does not change the asset refunded
+    let mut config = client.sale_config().unwrap();
+    let (new_token, _) = create_token(&env);
+    config.payment_token = new_token.clone();
+    client.configure(&admin, &config);
+
+    client.burn_for_refund(&alice, &1);
+    assert_eq!(payments.balance(&alice), 60);
+    assert_eq!(token::Client::new(&env, &new_token).balance(&alice), 0);
+}
+
+#[test]
+#[should_panic(expected = "Refund window has closed")]
+fn test_burn_for_refund_out_of_window() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+    let payments = setup_refunds(&env, &client, &admin);
+
+    let alice = Address::generate(&env);
+    StellarAssetClient::new(&env, &payments.address).mint(&alice, &100);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+
+    env.ledger().set_timestamp(3601);
+    client.burn_for_refund(&alice, &1);
+}
+
+#[test]
+fn test_minting_live() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+    assert!(client.minting_live());
+
+    // Pause
+    client.pause(&admin);
+    assert!(!client.minting_live());
+    client.unpause(&admin);
+    assert!(client.minting_live());
+
+    // Sale state and window
+    let (payment_token, _) = create_token(&env);
+    let mut config = SaleConfig {
+        payment_token,
+        treasury: admin.clone(),
+        price: 0,
+        sale_start: 100,
+        sale_end: 200,
+        sale_active: true,
+        holding_cap: Some(1),
+        royalty: Royalty { receiver: admin.clone(), bps: 0 },
+    };
+    client.configure(&admin, &config);
+    assert!(!client.minting_live());
+    env.ledger().set_timestamp(150);
+    assert!(client.minting_live());
+    config.sale_active = false;
+    client.configure(&admin, &config);
+    assert!(!client.minting_live());
+    config.sale_active = true;
+    client.configure(&admin, &config);
+
+    // Phase cap
+    client.set_phase_cap(&admin, &0, &0);
+    assert!(!client.minting_live());
+    client.set_phase_cap(&admin, &0, &10);
+    assert!(client.minting_live());
+
+    // Supply: every remaining public ID is reserved
+    client.set_reserved_range(&admin, &1, &2000);
+    assert!(!client.minting_live());
+}
+
+#[test]
+fn test_sell_through_royalty_marketplace() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let (payment_token, token_admin) = create_token(&env);
+    let artist = Address::generate(&env);
+    client.configure(
+        &admin,
+        &SaleConfig {
+            payment_token: payment_token.clone(),
+            treasury: admin.clone(),
+            price: 0,
+            sale_start: 0,
+            sale_end: 1000,
+            sale_active: true,
+            holding_cap: Some(5),
+            royalty: Royalty { receiver: artist.clone(), bps: 0 },
+        },
+    );
+    client.set_royalty(&admin, &artist, &1000, &true);
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let listed = Address::generate(&env);
+    let unlisted = Address::generate(&env);
+    client.add_to_whitelist(&admin, &seller);
+    client.mint(&seller);
+    client.set_approval_for_all(&seller, &listed, &true);
+    client.set_approval_for_all(&seller, &unlisted, &true);
+    token_admin.mint(&buyer, &500);
+
+    client.add_royalty_marketplace(&admin, &listed);
+    assert!(client.is_royalty_marketplace(&listed));
+    assert!(client.try_sell(&unlisted, &seller, &buyer, &1, &500).is_err());
+
+    // With royalties enforced, operators cannot bypass sell through transfer_from
+    assert!(client.try_transfer_from(&listed, &seller, &buyer, &1).is_err());
+    assert!(client.try_transfer_from(&unlisted, &seller, &buyer, &1).is_err());
+
+    // A price too large to take the royalty on is rejected rather than overflowing
+    let overflow = soroban_sdk::Error::from_contract_error(Error::PriceOverflow as u32);
+    assert_eq!(client.try_sell(&listed, &seller, &buyer, &1, &i128::MAX), Err(Ok(overflow)));
+
+    client.sell(&listed, &seller, &buyer, &1, &500);
+    let payments = token::Client::new(&env, &payment_token);
+    assert_eq!(client.owner_of(&1), buyer);
+    assert_eq!(payments.balance(&artist), 50);
+    assert_eq!(payments.balance(&seller), 450);
+}
+
+#[test]
+fn test_sell_requires_allowlisted_marketplace() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let (payment_token, _) = create_token(&env);
+    client.configure(
+        &admin,
+        &SaleConfig {
+            payment_token,
+            treasury: admin.clone(),
+            price: 0,
+            sale_start: 0,
+            sale_end: 1000,
+            sale_active: true,
+            holding_cap: Some(5),
+            royalty: Royalty { receiver: admin.clone(), bps: 0 },
+        },
+    );
+    assert!(!client.royalties_enforced());
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let operator = Address::generate(&env);
+    client.add_to_whitelist(&admin, &seller);
+    client.mint(&seller);
+    client.approve(&seller, &operator, &1);
+
+    // Even without enforced royalties, an approved operator that is not allowlisted cannot sell
+    assert!(client.try_sell(&operator, &seller, &buyer, &1, &0).is_err());
+    assert_eq!(client.owner_of(&1), seller);
+
+    client.add_royalty_marketplace(&admin, &operator);
+    client.sell(&operator, &seller, &buyer, &1, &0);
+    assert!(env.auths().iter().any(|(address, _)| *address == buyer));
+    assert_eq!(client.owner_of(&1), buyer);
+}
+
+#[test]
+fn test_total_transfers() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let operator = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.mint(&alice);
+    assert_eq!(client.total_transfers(), 0);
+
+    client.transfer(&alice, &bob, &1);
+    client.transfer(&bob, &alice, &1);
+    client.approve(&alice, &operator, &1);
+    client.transfer_from(&operator, &alice, &bob, &1);
+    assert_eq!(client.total_transfers(), 3);
+}
+
+fn merkle_leaf(env: &Env, address: &Address) -> BytesN<32> {
+    env.crypto().sha256(&address.clone().to_xdr(env)).into()
+}
+
+fn merkle_parent(env: &Env, a: &BytesN<32>, b: &BytesN<32>) -> BytesN<32> {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut pair = Bytes::new(env);
+    pair.extend_from_array(&first.to_array());
+    pair.extend_from_array(&second.to_array());
+    env.crypto().sha256(&pair).into()
+}
+
+#[test]
+fn test_verify_proof() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let carol = Address::generate(&env);
+    let alice_leaf = merkle_leaf(&env, &alice);
+    let bob_leaf = merkle_leaf(&env, &bob);
+
+    // Nobody is eligible before a root is set
+    assert!(!client.verify_proof(&alice, &vec![&env, bob_leaf.clone()]));
+    client.set_merkle_root(&admin, &merkle_parent(&env, &alice_leaf, &bob_leaf));
+
+    assert!(client.verify_proof(&alice, &vec![&env, bob_leaf.clone()]));
+    assert!(!client.verify_proof(&carol, &vec![&env, bob_leaf.clone()]));
+    assert!(!client.verify_proof(&alice, &vec![&env, alice_leaf.clone()]));
+
+    // Setting a root leaves the stored whitelist in charge until the mode is switched
+    assert_eq!(client.whitelist_mode(), 1);
+    client.set_whitelist_mode(&admin, &2);
+
+    // Verifying has no side effects; minting with the same proof still works
+    assert_eq!(client.last_mint_at(), None);
+    client.mint_with_proof(&alice, &vec![&env, bob_leaf]);
+    assert_eq!(client.owner_of(&1), alice);
+}
+
+#[test]
+fn test_whitelist_mode() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let listed = Address::generate(&env);
+    let stranger = Address::generate(&env);
+    client.add_to_whitelist(&admin, &listed);
+
+    // Stored whitelist by default
+    assert_eq!(client.whitelist_mode(), 1);
+    assert!(client.try_mint(&stranger).is_err());
+    client.mint(&listed);
+
+    // Open minting needs no whitelist entry, but the recipient must still authorize it
+    client.set_whitelist_mode(&admin, &0);
+    client.mint(&stranger);
+    assert!(env.auths().iter().any(|(address, _)| *address == stranger));
+
+    // Merkle mode only accepts proofs
+    let proven = Address::generate(&env);
+    let other = Address::generate(&env);
+    let proven_leaf = merkle_leaf(&env, &proven);
+    let other_leaf = merkle_leaf(&env, &other);
+    client.set_merkle_root(&admin, &merkle_parent(&env, &proven_leaf, &other_leaf));
+    client.set_whitelist_mode(&admin, &2);
+    assert!(client.try_mint(&proven).is_err());
+    client.mint_with_proof(&proven, &vec![&env, other_leaf.clone()]);
+
+    // Signature mode only accepts mints signed by the mint signer
+    let signer = SigningKey::from_bytes(&[9; 32]);
+    client.set_mint_signer(&admin, &BytesN::from_array(&env, &signer.verifying_key().to_bytes()));
+    client.set_whitelist_mode(&admin, &3);
+    assert!(client.try_mint_with_proof(&other, &vec![&env, proven_leaf]).is_err());
+    assert!(client.try_mint(&other).is_err());
+
+    let signature = sign_mint(&env, &client, &signer, &other, 100, 0);
+    client.mint_with_signature(&other, &100, &signature);
+    assert_eq!(client.owner_of(&4), other);
+    assert_eq!(client.mint_nonce(&other), 1);
+
+    // A signature is bound to its recipient
+    let forged = Address::generate(&env);
+    assert!(client.try_mint_with_signature(&forged, &100, &signature).is_err());
+
+    assert!(client.try_set_whitelist_mode(&admin, &4).is_err());
+}
+
+fn sign_mint(
+    env: &Env,
+    client: &StallionNFTClient,
+    signer: &SigningKey,
+    to: &Address,
+    deadline: u64,
+    nonce: u64,
+) -> BytesN<64> {
+    let payload = (client.address.clone(), to.clone(), deadline, nonce).to_xdr(env);
+    let message: std::vec::Vec<u8> = payload.iter().collect();
+    BytesN::from_array(env, &signer.sign(&message).to_bytes())
+}
+
+#[test]
+fn test_mint_signature_single_use() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let signer = SigningKey::from_bytes(&[9; 32]);
+    client.set_mint_signer(&admin, &BytesN::from_array(&env, &signer.verifying_key().to_bytes()));
+    client.set_whitelist_mode(&admin, &3);
+    client.set_mint_cap(&admin, &5);
+
+    // A signature mints once even while the address has cap left
+    let alice = Address::generate(&env);
+    let signature = sign_mint(&env, &client, &signer, &alice, 100, 0);
+    client.mint_with_signature(&alice, &100, &signature);
+    assert!(client.try_mint_with_signature(&alice, &100, &signature).is_err());
+
+    // The next mint needs a fresh signature over the next nonce
+    client.mint_with_signature(&alice, &100, &sign_mint(&env, &client, &signer, &alice, 100, 1));
+    assert_eq!(client.mint_count(&alice), 2);
+
+    // Signatures stop working after their deadline
+    let late = sign_mint(&env, &client, &signer, &alice, 100, 2);
+    env.ledger().set_timestamp(101);
+    assert!(client.try_mint_with_signature(&alice, &100, &late).is_err());
+}
+
+#[test]
+fn test_per_token_soulbound() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, admin) = setup(&env);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let carol = Address::generate(&env);
+    client.add_to_whitelist(&admin, &alice);
+    client.add_to_whitelist(&admin, &bob);
+    client.mint_soulbound(&alice);
+    client.mint(&bob);
+
+    assert!(client.is_soulbound(&1));
+    assert!(!client.is_soulbound(&2));
+
+    assert!(client.try_transfer(&alice, &carol, &1).is_err());
+    assert_eq!(client.owner_of(&1), alice);
+
+    client.transfer(&bob, &carol, &2);
+    assert_eq!(client.owner_of(&2), carol);
 }